@@ -0,0 +1,32 @@
+use std::fs;
+use std::hint::black_box;
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use id3rs::frame_index::FrameIndex;
+use id3rs::mp3_parser::Mp3FrameParser;
+
+const INFO_FRAME: usize = 384;
+
+/// About 4 MB of audio, the frames of the sample after its Info frame repeated
+fn stream() -> Vec<u8> {
+  let audio = fs::read("samples/4tink-raw.mp3").unwrap();
+  audio[INFO_FRAME..].repeat(400)
+}
+
+fn frames(c: &mut Criterion) {
+  let stream = stream();
+  let mut group = c.benchmark_group("frames");
+  group.throughput(Throughput::Bytes(stream.len() as u64));
+  group.bench_function("parser", |b| b.iter(|| {
+    Mp3FrameParser::from_reader(Cursor::new(black_box(&stream))).map(|frame| frame.unwrap().bitrate).sum::<u32>()
+  }));
+  group.bench_function("index", |b| b.iter(|| {
+    FrameIndex::new(black_box(&stream)).map(|entry| entry.header.bitrate).sum::<u32>()
+  }));
+  group.finish();
+}
+
+criterion_group!(benches, frames);
+criterion_main!(benches);
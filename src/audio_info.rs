@@ -0,0 +1,101 @@
+// Duration and bitrate of the MPEG audio following the tag
+
+use std::fs::File;
+use std::io;
+use std::io::{Seek, SeekFrom};
+
+use crate::mp3_frame::FrameHeader;
+use crate::mp3_parser::Mp3FrameParser;
+use crate::vbr_header::{VbrKind, VbrMethod};
+use crate::{ID3rs, Result, ID3HEADER_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitrateMode {
+  Constant,
+  /// ABR, as recorded by LAME
+  Average,
+  Variable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioInfo {
+  pub duration_ms: f64,
+  /// Audio frames, without a Xing/Info or VBRI frame
+  pub frames: u32,
+  pub sample_rate: u32,
+  pub bitrate_mode: BitrateMode,
+  pub average_kbps: f64,
+  /// Only known after scanning all frames, or from the first frame of a constant bitrate file
+  pub min_kbps: Option<u32>,
+  pub max_kbps: Option<u32>,
+  /// Computed from the Xing/Info or VBRI header instead of scanning all frames
+  pub from_header: bool,
+}
+
+impl AudioInfo {
+  /// Uses the Xing/Info or VBRI header in the first frame, and scans all frames when there is none
+  /// or it lacks the frame or byte count
+  pub fn read(tag: &ID3rs) -> Result<AudioInfo> {
+    let mut file = File::open(&tag.path)?;
+    let start = if tag.header_size > 0 { ID3HEADER_SIZE + tag.header_size } else { 0 };
+    file.seek(SeekFrom::Start(start))?;
+    let first = Mp3FrameParser::from_reader(&mut file).next().ok_or("No MPEG audio frame")??;
+    if let Some(info) = Self::from_header(&first) {
+      return Ok(info);
+    }
+    file.seek(SeekFrom::Start(start))?;
+    Self::scan(Mp3FrameParser::from_reader(file))
+  }
+
+  fn from_header(first: &FrameHeader) -> Option<AudioInfo> {
+    let header = first.vbr_header()?;
+    let frames = header.frames.filter(|frames| *frames > 0)?;
+    let duration_ms = frames as f64 * first.samples_per_frame() as f64 * 1000.0 / first.frequency as f64;
+    let bitrate_mode = match (header.kind, header.lame.as_ref().map(|lame| lame.vbr_method)) {
+      (VbrKind::Info, _) | (_, Some(VbrMethod::Cbr | VbrMethod::Cbr2Pass)) => BitrateMode::Constant,
+      (_, Some(VbrMethod::Abr | VbrMethod::Abr2Pass)) => BitrateMode::Average,
+      (_, _) => BitrateMode::Variable,
+    };
+    let (min_kbps, max_kbps, average_kbps) = match bitrate_mode {
+      BitrateMode::Constant => (Some(first.bitrate), Some(first.bitrate), first.bitrate as f64),
+      _ => (None, None, header.bytes?.saturating_sub(first.frame_size()) as f64 * 8.0 / duration_ms),
+    };
+    Some(AudioInfo { duration_ms, frames, sample_rate: first.frequency, bitrate_mode, average_kbps, min_kbps, max_kbps, from_header: true })
+  }
+
+  /// Adds up all frames, skipping a Xing/Info or VBRI frame at the start
+  pub fn scan(frames: impl Iterator<Item=io::Result<FrameHeader>>) -> Result<AudioInfo> {
+    let mut frames = frames.peekable();
+    if let Some(Ok(first)) = frames.peek() {
+      if first.vbr_header().is_some() {
+        frames.next();
+      }
+    }
+    let (mut count, mut bytes, mut duration_ms, mut min_kbps, mut max_kbps) = (0, 0u64, 0.0, u32::MAX, 0);
+    let mut sample_rate = 0;
+    for frame in frames {
+      let frame = frame?;
+      if count == 0 {
+        sample_rate = frame.frequency;
+      }
+      count += 1;
+      bytes += frame.frame_size() as u64;
+      duration_ms += frame.duration_ms();
+      min_kbps = min_kbps.min(frame.bitrate);
+      max_kbps = max_kbps.max(frame.bitrate);
+    }
+    if count == 0 {
+      Err("No MPEG audio frame")?
+    }
+    Ok(AudioInfo {
+      duration_ms,
+      frames: count,
+      sample_rate,
+      bitrate_mode: if min_kbps == max_kbps { BitrateMode::Constant } else { BitrateMode::Variable },
+      average_kbps: bytes as f64 * 8.0 / duration_ms,
+      min_kbps: Some(min_kbps),
+      max_kbps: Some(max_kbps),
+      from_header: false,
+    })
+  }
+}
@@ -0,0 +1,369 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::mixedinkey::{self, MixedInKey, CUE_POINTS_OBJECT};
+use crate::mp3_frame::frame_header;
+use crate::rekordbox::{self, Anlz, CueList, CueListKind, DAT_FILENAME};
+use crate::serato::{self, BeatGridMarker, Markers2, Markers2Entry, Rgb, Serato};
+use crate::traktor::{self, CueKind, Traktor};
+use crate::{ID3rs, Result, Tag, ID3HEADER_SIZE};
+
+// Vendor-neutral cue points, loops and beat grid. Positions are in milliseconds on the timeline of
+// a plain MPEG decoder, which includes the silent Xing/Info frame and the encoder delay. Serato,
+// Traktor and Mixed In Key use the same timeline. Rekordbox skips the first frame, unless it is a
+// Xing/Info frame with a valid LAME tag, so its positions are one frame (26 ms at 44.1 kHz) earlier.
+
+const SERATO_CUE_COLOR: Rgb = Rgb(0xcc, 0x00, 0x00);
+const SERATO_LOOP_COLOR: Rgb = Rgb(0x27, 0xaa, 0xe1);
+const TRAKTOR_GRID_NAME: &str = "AutoGrid";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vendor {
+  Serato,
+  Traktor,
+  Rekordbox,
+  MixedInKey,
+}
+
+/// In the order `ID3rs::cues` looks for them
+pub static VENDORS: [Vendor; 4] = [Vendor::Serato, Vendor::Traktor, Vendor::Rekordbox, Vendor::MixedInKey];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CuePoint {
+  /// Hot cue slot starting at 0, `None` for memory cues
+  pub index: Option<u8>,
+  pub position_ms: f64,
+  pub name: String,
+  pub color: Option<(u8, u8, u8)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+  pub index: Option<u8>,
+  pub start_ms: f64,
+  pub end_ms: f64,
+  pub name: String,
+  pub color: Option<(u8, u8, u8)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridMarker {
+  pub position_ms: f64,
+  pub bpm: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BeatGrid {
+  pub markers: Vec<GridMarker>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Cues {
+  pub cue_points: Vec<CuePoint>,
+  pub loops: Vec<Loop>,
+  pub beat_grid: Option<BeatGrid>,
+}
+
+/// What the vendors need to know about the start of the audio to agree on positions
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Mp3Timing {
+  pub frame_ms: f64,
+  /// First frame is a Xing/Info frame with a LAME tag of which the CRC matches
+  pub lame_tag: bool,
+}
+
+impl CuePoint {
+  pub fn new(index: Option<u8>, position_ms: f64, name: &str) -> CuePoint {
+    CuePoint { index, position_ms, name: name.to_string(), color: None }
+  }
+}
+
+impl Loop {
+  pub fn new(index: Option<u8>, start_ms: f64, end_ms: f64, name: &str) -> Loop {
+    Loop { index, start_ms, end_ms, name: name.to_string(), color: None }
+  }
+}
+
+impl Vendor {
+  /// Milliseconds to add to a neutral position to get the position this vendor stores
+  pub fn offset_ms(&self, timing: &Mp3Timing) -> f64 {
+    match self {
+      Vendor::Rekordbox if !timing.lame_tag => -timing.frame_ms,
+      _ => 0.0
+    }
+  }
+}
+
+impl Cues {
+  pub fn is_empty(&self) -> bool {
+    self.cue_points.is_empty() && self.loops.is_empty() && self.beat_grid.is_none()
+  }
+
+  /// The cues stored by `vendor`, or `None` when the tag has no frames of that vendor
+  pub fn read(tag: &Tag, vendor: Vendor, timing: &Mp3Timing) -> Result<Option<Cues>> {
+    let cues = match vendor {
+      Vendor::Serato => read_serato(tag)?,
+      Vendor::Traktor => read_traktor(tag)?,
+      Vendor::Rekordbox => read_rekordbox(tag)?,
+      Vendor::MixedInKey => read_mixedinkey(tag)?,
+    };
+    Ok(cues.map(|cues| cues.shifted(-vendor.offset_ms(timing))))
+  }
+
+  pub fn write(&self, tag: &mut Tag, vendor: Vendor, timing: &Mp3Timing) -> Result<()> {
+    let cues = self.shifted(vendor.offset_ms(timing));
+    match vendor {
+      Vendor::Serato => write_serato(tag, &cues),
+      Vendor::Traktor => write_traktor(tag, &cues),
+      Vendor::Rekordbox => write_rekordbox(tag, &cues),
+      Vendor::MixedInKey => write_mixedinkey(tag, &cues),
+    }
+  }
+
+  fn shifted(&self, offset_ms: f64) -> Cues {
+    let mut cues = self.clone();
+    cues.cue_points.iter_mut().for_each(|cue| cue.position_ms += offset_ms);
+    cues.loops.iter_mut().for_each(|lp| {
+      lp.start_ms += offset_ms;
+      lp.end_ms += offset_ms;
+    });
+    if let Some(grid) = cues.beat_grid.as_mut() {
+      grid.markers.iter_mut().for_each(|marker| marker.position_ms += offset_ms);
+    }
+    cues
+  }
+}
+
+impl Mp3Timing {
+  /// Looks at the first audio frame following the tag, the defaults for a tag that was not read from a file
+  pub fn read(tag: &ID3rs) -> Result<Mp3Timing> {
+    if tag.path.as_os_str().is_empty() {
+      return Ok(Mp3Timing::default());
+    }
+    let mut file = File::open(&tag.path)?;
+    if tag.header_size > 0 {
+      file.seek(SeekFrom::Start(ID3HEADER_SIZE + tag.header_size))?;
+    }
+    let mut buffer = vec![];
+    file.take(8192).read_to_end(&mut buffer)?;
+    Ok(Mp3Timing::parse(&buffer))
+  }
+
+  pub fn parse(audio: &[u8]) -> Mp3Timing {
+    let start = match audio.windows(2).position(|w| w[0] == 0xff && w[1] & 0xe0 == 0xe0) {
+      Some(start) => start,
+      None => return Mp3Timing::default()
+    };
+    match frame_header(&audio[start..]) {
+      Ok((_, frame)) => Mp3Timing {
+        frame_ms: frame.duration_ms(),
+        lame_tag: frame.vbr_header().and_then(|header| header.lame).is_some_and(|lame| lame.crc_ok),
+      },
+      Err(_) => Mp3Timing::default()
+    }
+  }
+}
+
+fn rgb(color: &Rgb) -> Option<(u8, u8, u8)> {
+  Some((color.0, color.1, color.2))
+}
+
+fn read_serato(tag: &Tag) -> Result<Option<Cues>> {
+  let serato = Serato::read(tag)?;
+  if serato.markers.is_none() && serato.beatgrid.is_none() {
+    return Ok(None);
+  }
+  let mut cues = Cues::default();
+  if let Some(markers) = &serato.markers {
+    cues.cue_points = markers.cues().into_iter()
+      .map(|cue| CuePoint { index: Some(cue.index), position_ms: cue.position_ms as f64, name: cue.name.clone(), color: rgb(&cue.color) })
+      .collect();
+    cues.loops = markers.loops().into_iter()
+      .map(|lp| Loop { index: Some(lp.index), start_ms: lp.start_ms as f64, end_ms: lp.end_ms as f64, name: lp.name.clone(), color: rgb(&lp.color) })
+      .collect();
+  }
+  if let Some(grid) = &serato.beatgrid {
+    let markers = grid.markers.iter().enumerate().map(|(index, marker)| match marker {
+      BeatGridMarker::NonTerminal { position, beats_till_next } => {
+        let next = match grid.markers.get(index + 1) {
+          Some(BeatGridMarker::NonTerminal { position, .. }) | Some(BeatGridMarker::Terminal { position, .. }) => *position,
+          None => *position,
+        };
+        let bpm = if next > *position { *beats_till_next as f64 * 60.0 / (next - position) as f64 } else { 0.0 };
+        GridMarker { position_ms: *position as f64 * 1000.0, bpm }
+      }
+      BeatGridMarker::Terminal { position, bpm } => GridMarker { position_ms: *position as f64 * 1000.0, bpm: *bpm as f64 },
+    }).collect();
+    cues.beat_grid = Some(BeatGrid { markers });
+  }
+  Ok(Some(cues))
+}
+
+fn write_serato(tag: &mut Tag, cues: &Cues) -> Result<()> {
+  let existing = Serato::read(tag)?;
+  let mut markers = existing.markers.unwrap_or_else(|| Markers2 {
+    entries: vec![Markers2Entry::Color(Rgb(0xff, 0xff, 0xff)), Markers2Entry::BpmLock(false)],
+    ..Default::default()
+  });
+  // Serato only has hot cues, memory cues are left out
+  let serato_cues = cues.cue_points.iter().filter_map(|cue| Some(serato::Cue {
+    index: cue.index?,
+    position_ms: cue.position_ms.max(0.0).round() as u32,
+    color: cue.color.map(|(r, g, b)| Rgb(r, g, b)).unwrap_or(SERATO_CUE_COLOR),
+    name: cue.name.clone(),
+  })).collect();
+  let mut slots = free_slots(cues.loops.iter().map(|lp| lp.index));
+  let serato_loops = cues.loops.iter().map(|lp| Ok(serato::Loop {
+    index: match lp.index {
+      Some(index) => index,
+      None => slots.next().ok_or("No free Serato loop slot")?,
+    },
+    start_ms: lp.start_ms.max(0.0).round() as u32,
+    end_ms: lp.end_ms.max(0.0).round() as u32,
+    color: lp.color.map(|(r, g, b)| Rgb(r, g, b)).unwrap_or(SERATO_LOOP_COLOR),
+    locked: false,
+    name: lp.name.clone(),
+  })).collect::<Result<_>>()?;
+  markers.set_cues(serato_cues, serato_loops);
+
+  let beatgrid = cues.beat_grid.as_ref().map(|grid| {
+    let markers = grid.markers.iter().enumerate().map(|(index, marker)| {
+      let position = (marker.position_ms / 1000.0) as f32;
+      match grid.markers.get(index + 1) {
+        Some(next) => {
+          let beats = ((next.position_ms - marker.position_ms) * marker.bpm / 60000.0).round() as u32;
+          BeatGridMarker::NonTerminal { position, beats_till_next: beats }
+        }
+        None => BeatGridMarker::Terminal { position, bpm: marker.bpm as f32 },
+      }
+    }).collect();
+    serato::BeatGrid { markers, footer: existing.beatgrid.as_ref().map(|g| g.footer).unwrap_or(0) }
+  });
+  Serato { markers: Some(markers), beatgrid, autotags: None, overview: None, analysis: None }.write(tag);
+  Ok(())
+}
+
+/// Serato needs a slot for every loop, loops without one get the ones that are not taken
+fn free_slots(taken: impl Iterator<Item=Option<u8>>) -> impl Iterator<Item=u8> {
+  let taken: Vec<u8> = taken.flatten().collect();
+  (0..=u8::MAX).filter(move |slot| !taken.contains(slot))
+}
+
+fn read_traktor(tag: &Tag) -> Result<Option<Cues>> {
+  let traktor = match Traktor::read(tag)? {
+    Some(traktor) => traktor,
+    None => return Ok(None)
+  };
+  let index = |hotcue: i32| u8::try_from(hotcue).ok();
+  let mut cues = Cues::default();
+  for cue in traktor.all_cues()? {
+    match cue.kind {
+      CueKind::Grid => {
+        let bpm = traktor.bpm().unwrap_or_default() as f64;
+        cues.beat_grid.get_or_insert_with(BeatGrid::default).markers.push(GridMarker { position_ms: cue.start_ms, bpm });
+      }
+      CueKind::Loop => cues.loops.push(Loop {
+        index: index(cue.hotcue),
+        start_ms: cue.start_ms,
+        end_ms: cue.start_ms + cue.length_ms,
+        name: cue.name,
+        color: None,
+      }),
+      _ => cues.cue_points.push(CuePoint { index: index(cue.hotcue), position_ms: cue.start_ms, name: cue.name, color: None }),
+    }
+  }
+  Ok(Some(cues))
+}
+
+/// Traktor analysis cannot be created from scratch, so this only updates an existing one
+fn write_traktor(tag: &mut Tag, cues: &Cues) -> Result<()> {
+  let mut traktor = Traktor::read(tag)?.ok_or("No Traktor analysis to update")?;
+  let hotcue = |index: Option<u8>| index.map(|i| i as i32).unwrap_or(-1);
+  let mut result = match &cues.beat_grid {
+    Some(grid) => grid.markers.iter()
+      .map(|marker| traktor::Cue::new(TRAKTOR_GRID_NAME, CueKind::Grid, marker.position_ms, 0.0, -1))
+      .collect(),
+    None => traktor.grid_markers()?,
+  };
+  result.extend(cues.cue_points.iter()
+    .map(|cue| traktor::Cue::new(&cue.name, CueKind::Cue, cue.position_ms, 0.0, hotcue(cue.index))));
+  result.extend(cues.loops.iter()
+    .map(|lp| traktor::Cue::new(&lp.name, CueKind::Loop, lp.start_ms, lp.end_ms - lp.start_ms, hotcue(lp.index))));
+  traktor.set_cues(&result)?;
+  traktor.write(tag);
+  Ok(())
+}
+
+fn read_rekordbox(tag: &Tag) -> Result<Option<Cues>> {
+  let anlz = match Anlz::read(tag, DAT_FILENAME)? {
+    Some(anlz) => anlz,
+    None => return Ok(None)
+  };
+  let index = |hot_cue: u32| hot_cue.checked_sub(1).map(|i| i as u8);
+  let mut cues = Cues::default();
+  for cue in anlz.cues()? {
+    match (cue.kind, cue.loop_end_ms) {
+      (rekordbox::CueKind::Loop, Some(end)) => cues.loops.push(Loop {
+        index: index(cue.hot_cue),
+        start_ms: cue.time_ms as f64,
+        end_ms: end as f64,
+        name: cue.comment,
+        color: cue.color,
+      }),
+      _ => cues.cue_points.push(CuePoint { index: index(cue.hot_cue), position_ms: cue.time_ms as f64, name: cue.comment, color: cue.color }),
+    }
+  }
+  let mut markers: Vec<GridMarker> = vec![];
+  for beat in anlz.beat_grid()? {
+    if markers.last().map(|m| m.bpm) != Some(beat.bpm() as f64) {
+      markers.push(GridMarker { position_ms: beat.time_ms as f64, bpm: beat.bpm() as f64 });
+    }
+  }
+  if !markers.is_empty() {
+    cues.beat_grid = Some(BeatGrid { markers });
+  }
+  Ok(Some(cues))
+}
+
+/// Writes the cue lists only, a rekordbox beat grid has an entry for every beat of the track
+fn write_rekordbox(tag: &mut Tag, cues: &Cues) -> Result<()> {
+  let mut anlz = Anlz::read(tag, DAT_FILENAME)?.unwrap_or_default();
+  let cue = |index: Option<u8>, kind, start_ms: f64, end_ms: Option<f64>, name: &str, color| rekordbox::Cue {
+    hot_cue: index.map(|i| i as u32 + 1).unwrap_or(0),
+    kind,
+    time_ms: start_ms.max(0.0).round() as u32,
+    loop_end_ms: end_ms.map(|end| end.max(0.0).round() as u32),
+    color_id: 0,
+    color,
+    comment: name.to_string(),
+  };
+  let all: Vec<rekordbox::Cue> = cues.cue_points.iter()
+    .map(|c| cue(c.index, rekordbox::CueKind::Cue, c.position_ms, None, &c.name, c.color))
+    .chain(cues.loops.iter().map(|l| cue(l.index, rekordbox::CueKind::Loop, l.start_ms, Some(l.end_ms), &l.name, l.color)))
+    .collect();
+  let (hot, memory) = all.into_iter().partition(|c| c.hot_cue > 0);
+  anlz.set_cue_lists(&[
+    CueList { kind: CueListKind::HotCue, cues: hot },
+    CueList { kind: CueListKind::Memory, cues: memory },
+  ]);
+  anlz.write(tag, DAT_FILENAME);
+  Ok(())
+}
+
+fn read_mixedinkey(tag: &Tag) -> Result<Option<Cues>> {
+  if tag.object_by_description(CUE_POINTS_OBJECT).is_none() {
+    return Ok(None);
+  }
+  let cue_points = MixedInKey::read(tag)?.cues.into_iter().enumerate()
+    .map(|(index, cue)| CuePoint { index: Some(index as u8), position_ms: cue.time_ms, name: cue.name, color: None })
+    .collect();
+  Ok(Some(Cues { cue_points, ..Default::default() }))
+}
+
+/// Mixed In Key only knows cue points, loops and beat grid are left out
+fn write_mixedinkey(tag: &mut Tag, cues: &Cues) -> Result<()> {
+  let cues = cues.cue_points.iter()
+    .map(|cue| mixedinkey::Cue { name: cue.name.clone(), time_ms: cue.position_ms })
+    .collect();
+  MixedInKey { cues, ..Default::default() }.write(tag)
+}
@@ -3,6 +3,8 @@ use std::ffi::{c_char, CStr};
 
 // https://jakegoulding.com/rust-ffi-omnibus/objects/
 
+/// # Safety
+/// `file` must be a valid NUL terminated path.
 #[no_mangle]
 pub unsafe extern "C" fn id3_read(file: *const c_char) -> *mut ID3rs {
   assert!(!file.is_null());
@@ -11,6 +13,8 @@ pub unsafe extern "C" fn id3_read(file: *const c_char) -> *mut ID3rs {
   Box::into_raw(id3)
 }
 
+/// # Safety
+/// `ptr` must come from `id3_read` and `file` must be a valid NUL terminated path.
 #[no_mangle]
 pub unsafe extern "C" fn id3_write(ptr: *mut ID3rs, file: *const c_char) {
   assert!(!ptr.is_null());
@@ -20,6 +24,8 @@ pub unsafe extern "C" fn id3_write(ptr: *mut ID3rs, file: *const c_char) {
   id3rs.write_to(file).unwrap();
 }
 
+/// # Safety
+/// `ptr` must come from `id3_read` and `email` must be a valid NUL terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn id3_set_popularity(ptr: *mut ID3rs, email: *const c_char, rating: u8) {
   assert!(!ptr.is_null());
@@ -29,6 +35,8 @@ pub unsafe extern "C" fn id3_set_popularity(ptr: *mut ID3rs, email: *const c_cha
   id3rs.set_popularity(email, rating);
 }
 
+/// # Safety
+/// `ptr` must come from `id3_read` and `group` must be a valid NUL terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn id3_set_grouping(ptr: *mut ID3rs, group: *const c_char) {
   assert!(!ptr.is_null());
@@ -38,6 +46,8 @@ pub unsafe extern "C" fn id3_set_grouping(ptr: *mut ID3rs, group: *const c_char)
   id3rs.set_grouping(group);
 }
 
+/// # Safety
+/// `ptr` must come from `id3_read` and must not be used afterwards.
 #[no_mangle]
 pub unsafe extern "C" fn id3_free(ptr: *mut ID3rs) {
   if ptr.is_null() { return; }
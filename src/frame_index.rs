@@ -0,0 +1,103 @@
+// Finds the MPEG frames of a stream that is in memory, without copying them
+
+use nom::Err::Incomplete;
+
+use crate::mp3_frame::{frame_info, FrameHeader};
+use crate::mp3_parser::Resync;
+
+/// A frame of the stream, the `data` of the `header` is empty
+#[derive(Debug, PartialEq)]
+pub struct FrameEntry {
+  pub offset: u64,
+  pub len: u32,
+  pub header: FrameHeader,
+}
+
+impl FrameEntry {
+  /// The bytes of this frame in `stream`, the data the index was built from
+  pub fn data<'a>(&self, stream: &'a [u8]) -> &'a [u8] {
+    &stream[self.offset as usize..self.offset as usize + self.len as usize]
+  }
+
+  /// The header with a copy of the frame data, for `crc_ok()` and `vbr_header()`
+  pub fn to_frame_header(&self, stream: &[u8]) -> FrameHeader {
+    let data = self.data(stream);
+    let (_, mut header) = frame_info(data).expect("indexed frame");
+    header.data = data.to_vec();
+    header
+  }
+}
+
+/// Iterates the frames of `stream`, like `Mp3FrameParser` but without allocating for the audio
+pub struct FrameIndex<'a> {
+  stream: &'a [u8],
+  position: usize,
+  skipping: Option<usize>,
+  resyncs: Vec<Resync>,
+}
+
+impl<'a> FrameIndex<'a> {
+  /// Offsets count from the start of `stream`, which can be a memory-mapped file
+  pub fn new(stream: &'a [u8]) -> Self {
+    FrameIndex { stream, position: 0, skipping: None, resyncs: vec![] }
+  }
+
+  /// Where sync was lost so far
+  pub fn resyncs(&self) -> &[Resync] {
+    &self.resyncs
+  }
+
+  fn skip(&mut self, to: usize) {
+    if to > self.position {
+      self.skipping.get_or_insert(self.position);
+      self.position = to;
+    }
+  }
+
+  fn synced(&mut self) {
+    if let Some(start) = self.skipping.take() {
+      self.resyncs.push(Resync { offset: start as u64, len: (self.position - start) as u64 });
+    }
+  }
+}
+
+impl Iterator for FrameIndex<'_> {
+  type Item = FrameEntry;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let rest = &self.stream[self.position..];
+      let Some(sync) = rest.windows(2).position(|word| word[0] == 0xff && word[1] & 0xe0 == 0xe0) else {
+        self.skip(self.stream.len());
+        self.synced();
+        return None;
+      };
+      self.skip(self.position + sync);
+      match frame_info(&self.stream[self.position..]) {
+        Ok((_, header)) => {
+          self.synced();
+          let entry = FrameEntry { offset: self.position as u64, len: header.frame_size(), header };
+          self.position += entry.len as usize;
+          return Some(entry);
+        }
+        Err(Incomplete(_)) => {
+          self.skip(self.stream.len());
+          self.synced();
+          return None;
+        }
+        Err(_) => self.skip(self.position + 1),
+      }
+    }
+  }
+}
+
+/// Maps the file read-only, to index large files without reading them into memory
+///
+/// # Safety
+///
+/// The file must not be truncated or modified while the map is alive.
+#[cfg(feature = "mmap")]
+pub unsafe fn map_file(path: impl AsRef<std::path::Path>) -> std::io::Result<memmap2::Mmap> {
+  let file = std::fs::File::open(path)?;
+  memmap2::Mmap::map(&file)
+}
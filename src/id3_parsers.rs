@@ -4,7 +4,7 @@ use log::debug;
 use nom::branch::alt;
 use nom::bytes::streaming::{tag, take};
 use nom::character::streaming::one_of;
-use nom::combinator::{eof, map};
+use nom::combinator::{consumed, eof, map};
 use nom::IResult;
 use nom::multi::{fold_many_m_n, many_till};
 use nom::number::complete::be_u32;
@@ -34,7 +34,20 @@ pub fn all_frames(len: fn(&[u8]) -> IResult<&[u8], u32>)
     -> IResult<&[u8], Vec<Frame>> {
   move |input| {
     map(
-      many_till(alt((
+      all_raw_frames(len),
+      |frames| frames.into_iter().map(|(_, frame)| frame).collect())(input)
+  }
+}
+
+/// Each frame with the bytes it was parsed from
+pub type RawFrames<'a> = Vec<(&'a [u8], Frame)>;
+
+pub fn all_raw_frames(len: fn(&[u8]) -> IResult<&[u8], u32>)
+  -> impl for<'a> FnMut(&'a [u8])
+    -> IResult<&'a [u8], RawFrames<'a>> {
+  move |input| {
+    map(
+      many_till(consumed(alt((
         padding,
         extended_text_frame(len),
         comment_frame(len),
@@ -42,7 +55,7 @@ pub fn all_frames(len: fn(&[u8]) -> IResult<&[u8], u32>)
         picture_frame(len),
         text_frame(len),
         popularity_frame(len),
-        generic_frame(len))),
+        generic_frame(len)))),
         eof),
       |(frames, _)| frames)(input)
   }
@@ -1,12 +1,15 @@
 use std::fs;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
 use log::{debug, LevelFilter};
 
-use crate::id3_parsers::{all_frames, as_syncsafe, file_header, v23_len, v24_len};
+use crate::audio_info::AudioInfo;
+use crate::id3_parsers::{all_raw_frames, as_syncsafe, file_header, v23_len, v24_len};
+use crate::cue_points::{Cues, Mp3Timing, Vendor, VENDORS};
+use crate::musical_key::{KeyNotation, MusicalKey, EXTENDED_KEY_NAMES};
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
@@ -21,16 +24,34 @@ pub static TRACK_TAG: &str = "TRCK";
 pub static POPULARITY_TAG: &str = "POPM";
 pub static GENRE_TAG: &str = "TCON";
 pub static KEY_TAG: &str = "TKEY";
+pub static BPM_TAG: &str = "TBPM";
+pub static LENGTH_TAG: &str = "TLEN";
 pub static COMMENT_TAG: &str = "COMM";
 pub static OBJECT_TAG: &str = "GEOB";
 pub static GROUPING_TAG: &str = "GRP1";
 pub static EXTENDED_TAG: &str = "TXXX";
 pub static PICTURE_TAG: &str = "APIC";
+pub static PRIVATE_TAG: &str = "PRIV";
 
 pub mod mp3_frame;
 pub mod id3_parsers;
 pub mod ffi;
 pub mod mp3_parser;
+pub mod frame_index;
+pub mod vbr_header;
+pub mod musical_key;
+pub mod mixedinkey;
+pub mod serato;
+pub mod traktor;
+pub mod rekordbox;
+pub mod cue_points;
+pub mod rekordbox_xml;
+pub mod traktor_nml;
+pub mod picture;
+pub mod audio_info;
+pub mod validator;
+
+pub use picture::Picture;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Header {
@@ -40,7 +61,7 @@ pub struct Header {
   pub tag_size: u32,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Frame {
   Generic {
     id: String,
@@ -100,18 +121,73 @@ pub enum Frame {
 }
 
 
+/// An MP3 file and its tag, dereferences to the [Tag]
 pub struct ID3rs {
   pub path: PathBuf,
   pub header_size: u64,
+  pub tag: Tag,
+}
+
+/// The frames of an ID3v2 tag, independent of any file
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Tag {
   pub frames: Vec<Frame>,
   pub dirty: bool,
+  /// Version and bytes of the parsed frames, written verbatim while the frame is unchanged
+  version: u8,
+  original: Vec<(Frame, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteMode {
+  /// Writes the complete file next to the target and renames it over the target, keeping the
+  /// permissions, owner and modification time of the target. When the new tag of the file itself
+  /// fits in the old one, only that tag is overwritten and synced, so the audio is never at risk
+  /// and large files are not copied.
+  #[default]
+  Safe,
+  /// Truncates and rewrites the target, a failure halfway loses the audio. When the new tag fits
+  /// in the old one only the tag is overwritten.
+  InPlace,
+}
+
+/// How much padding follows the frames of a written tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaddingPolicy {
+  /// Keeps the size of the existing tag when the frames fit, otherwise grows the tag to a multiple of
+  /// [ID3HEADER_ALIGN] with more than that in padding.
+  #[default]
+  Keep,
+  /// Exactly this many bytes
+  Fixed(u64),
+  /// Up to the next multiple of this many bytes, counting the header
+  Align(u64),
+  /// No padding at all
+  Minimal,
+  /// Like `Keep`, but never more than this many bytes
+  ShrinkOver(u64),
+}
+
+impl PaddingPolicy {
+  /// Padding after `frames` bytes of frames, for a file whose existing tag is `existing` bytes
+  pub fn padding(&self, frames: u64, existing: u64) -> u64 {
+    match *self {
+      PaddingPolicy::Keep => padding_for(frames, existing),
+      PaddingPolicy::Fixed(size) => size,
+      PaddingPolicy::Align(align) => {
+        let align = align.max(1);
+        (align - (ID3HEADER_SIZE + frames) % align) % align
+      }
+      PaddingPolicy::Minimal => 0,
+      PaddingPolicy::ShrinkOver(size) => padding_for(frames, existing).min(size),
+    }
+  }
 }
 
-pub enum Picture {
-  Icon = 1,
-  OtherIcon = 2,
-  FrontCover = 3,
-  BackCover = 4,
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+  pub mode: WriteMode,
+  pub padding: PaddingPolicy,
 }
 
 pub const ID3HEADER_SIZE: u64 = 10;
@@ -120,186 +196,348 @@ pub const ID3HEADER_ALIGN: u64 = 512;
 impl ID3rs {
   pub fn read(path: impl Into<PathBuf>) -> Result<ID3rs> {
     let path = path.into();
-    let (mut file, header) = Self::read_header(&path)?;
-    match header {
+    let mut tag = Self::read_from(&mut File::open(&path)?)?;
+    tag.path = path;
+    Ok(tag)
+  }
+
+  /// Reads the tag from the start of `reader`, the `path` of the result is empty.
+  pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<ID3rs> {
+    let path = PathBuf::new();
+    let start = reader.stream_position()?;
+    match Tag::read_header(reader)? {
       Some(header) => {
         let mut input = vec![0u8; header.tag_size as usize];
-        file.read_exact(&mut input)?;
-
-        let (_, result) = match header.version {
-          3 => all_frames(v23_len)(&input).map_err(|_| "Frames error")?,
-          4 => all_frames(v24_len)(&input).map_err(|_| "Frames error")?,
-          v => Err(format!("Invalid version: {}", v))?
-        };
-
-        Ok(ID3rs { path, header_size: header.tag_size as u64, frames: result, dirty: false })
+        reader.read_exact(&mut input)?;
+        let tag = Tag::parse_frames(header.version, &input)?;
+        Ok(ID3rs { path, header_size: header.tag_size as u64, tag })
+      }
+      None => {
+        reader.seek(SeekFrom::Start(start))?;
+        Ok(ID3rs { path, header_size: 0, tag: Tag::new() })
       }
-      None => Ok(ID3rs { path, header_size: 0, frames: vec![], dirty: false })
     }
   }
 
-  fn read_header(path: impl AsRef<Path>) -> Result<(File, Option<Header>)> {
-    let mut file = File::open(path)?;
-    let mut buffer = [0; ID3HEADER_SIZE as usize];
-    file.read_exact(&mut buffer)?;
-    let header = file_header(&buffer).ok().map(|(_, header)| header);
-    Ok((file, header))
+  /// Writes the tag to the file it was read from, see [WriteMode::Safe]
+  pub fn write(&mut self) -> Result<()> {
+    self.write_with(&WriteOptions::default())
   }
 
-  pub fn write(&mut self) -> Result<()> {
-    self.write_to(&self.path)?;
+  pub fn write_with(&mut self, options: &WriteOptions) -> Result<()> {
+    self.write_to_with(&self.path, options)?;
+    self.header_size = Tag::read_header(&mut File::open(&self.path)?)?.map_or(0, |header| header.tag_size as u64);
     self.dirty = false;
     Ok(())
   }
 
   pub fn write_to(&self, target: impl AsRef<Path>) -> Result<()> {
-    let (mut file, header) = Self::read_header(&*self.path)?;
-
-    let mut tmp: File = tempfile::tempfile()?;
+    self.write_to_with(target, &WriteOptions::default())
+  }
 
-    let overwrite = <PathBuf as AsRef<Path>>::as_ref(&self.path) == target.as_ref();
-    let mut out = if overwrite {
-      if let Some(header) = &header {
-        file.seek(SeekFrom::Start(ID3HEADER_SIZE + header.tag_size as u64))?; // skip header and tag
-      } else {
-        file.seek(SeekFrom::Start(0))?;
+  pub fn write_to_with(&self, target: impl AsRef<Path>, options: &WriteOptions) -> Result<()> {
+    let target = target.as_ref();
+    if <PathBuf as AsRef<Path>>::as_ref(&self.path) == target && self.write_tag_region(options)? {
+      return Ok(());
+    }
+    let mut file = File::open(&self.path)?;
+
+    match options.mode {
+      WriteMode::Safe => {
+        let existing = fs::metadata(target).ok();
+        let dir = target.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let mut tmp = tempfile::Builder::new().prefix(".id3rs").suffix(".tmp").tempfile_in(dir)?;
+        self.write_to_writer_with(&mut file, tmp.as_file_mut(), options)?;
+        fs::set_permissions(tmp.path(), existing.as_ref().unwrap_or(&file.metadata()?).permissions())?;
+        if let Some(metadata) = &existing {
+          #[cfg(unix)]
+          {
+            use std::os::unix::fs::MetadataExt;
+            // fails unless running as root or as the owner
+            if let Err(e) = std::os::unix::fs::chown(tmp.path(), Some(metadata.uid()), Some(metadata.gid())) {
+              debug!("keeping new owner: {}", e);
+            }
+          }
+          tmp.as_file().set_modified(metadata.modified()?)?;
+        }
+        tmp.as_file().sync_all()?;
+        tmp.persist(target)?;
+      }
+      WriteMode::InPlace => {
+        if <PathBuf as AsRef<Path>>::as_ref(&self.path) == target {
+          let mut tmp: File = tempfile::tempfile()?;
+          std::io::copy(&mut file, &mut tmp)?;
+          tmp.seek(SeekFrom::Start(0))?;
+          let mut out = OpenOptions::new().write(true).truncate(true).open(&self.path)?;
+          self.write_to_writer_with(&mut tmp, &mut out, options)?;
+        } else {
+          self.write_to_writer_with(&mut file, &mut File::create(target)?, options)?;
+        }
       }
-      std::io::copy(&mut file, &mut tmp)?;
-      OpenOptions::new().write(true).truncate(true).open(&self.path)?
-    } else {
-      File::create(&target)?
+    }
+    Ok(())
+  }
+
+  /// Overwrites only the tag of the file when the new frames fit in it, leaving the audio untouched.
+  /// A failure halfway can only damage the tag.
+  fn write_tag_region(&self, options: &WriteOptions) -> Result<bool> {
+    let mut file = OpenOptions::new().read(true).write(true).open(&self.path)?;
+    let Some(header) = Tag::read_header(&mut file).ok().flatten() else {
+      return Ok(false);
     };
+    let version = self.tag.version();
+    let frames = self.tag.frame_bytes(version)?;
+    let padding = options.padding.padding(frames.len() as u64, header.tag_size as u64);
+    if frames.len() as u64 + padding != header.tag_size as u64 {
+      return Ok(false);
+    }
+    debug!("overwriting tag of {} in place", header.tag_size);
+    let modified = file.metadata()?.modified()?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&Tag::with_header(version, frames, padding as usize))?;
+    if options.mode == WriteMode::Safe {
+      file.set_modified(modified)?;
+    }
+    file.sync_all()?;
+    Ok(true)
+  }
 
-    out.write_all(b"ID3\x04\x00\x00FAKE")?;
+  /// Writes the tag to `out`, followed by the audio of `input` without its original tag.
+  pub fn write_to_writer<R: Read, W: Write>(&self, input: &mut R, out: &mut W) -> Result<()> {
+    self.write_to_writer_with(input, out, &WriteOptions::default())
+  }
 
-    ID3rs::write_id3_frames(&self.frames, &mut out)?;
+  /// Like `write_to_writer`, only the padding of the `options` applies.
+  pub fn write_to_writer_with<R: Read, W: Write>(&self, input: &mut R, out: &mut W, options: &WriteOptions) -> Result<()> {
+    let mut buffer = vec![];
+    input.by_ref().take(ID3HEADER_SIZE).read_to_end(&mut buffer)?;
+    let header = file_header(&buffer).ok().map(|(_, header)| header);
 
-    let header_size = self.write_padding(&mut out)?;
+    let version = self.tag.version();
+    let frames = self.tag.frame_bytes(version)?;
+    let padding = options.padding.padding(frames.len() as u64, header.as_ref().map_or(0, |header| header.tag_size as u64));
+    debug!("new tag size {}", frames.len() as u64 + padding);
+    out.write_all(&Tag::with_header(version, frames, padding as usize))?;
 
-    debug!("new tag size {}", header_size);
-    let vec = as_syncsafe(header_size as u32);
-    out.seek(SeekFrom::Start(6))?;
-    out.write_all(&vec)?;
-    out.seek(SeekFrom::Start(ID3HEADER_SIZE + header_size))?;
+    match header {
+      Some(header) => {
+        std::io::copy(&mut input.take(header.tag_size as u64), &mut std::io::sink())?; // skip header and tag
+      }
+      None => out.write_all(&buffer)?,
+    }
+    std::io::copy(input, out)?;
+    Ok(())
+  }
+
+  /// Duration and bitrate of the audio following the tag
+  pub fn audio_info(&self) -> Result<AudioInfo> {
+    AudioInfo::read(self)
+  }
+
+  /// Sets `TLEN` to the duration of the audio, returns that duration
+  pub fn update_length(&mut self) -> Result<AudioInfo> {
+    let info = AudioInfo::read(self)?;
+    self.set_length(info.duration_ms.round() as u64);
+    Ok(info)
+  }
 
-    if overwrite {
-      tmp.seek(SeekFrom::Start(0))?;
-      std::io::copy(&mut tmp, &mut out)?;
-    } else {
-      if let Some(header) = header {
-        file.seek(SeekFrom::Start(ID3HEADER_SIZE + header.tag_size as u64))?;
+  /// Cues of the first vendor in [VENDORS] that has any, vendor frames that do not parse are skipped.
+  pub fn cues(&self) -> Result<Cues> {
+    let timing = Mp3Timing::read(self)?;
+    for vendor in VENDORS {
+      match Cues::read(self, vendor, &timing) {
+        Ok(Some(cues)) if !cues.is_empty() => return Ok(cues),
+        Ok(_) => {}
+        Err(e) => debug!("skipping {:?} cues: {}", vendor, e),
       }
+    }
+    Ok(Cues::default())
+  }
 
-      std::io::copy(&mut file, &mut out)?;
-    };
+  pub fn cues_from(&self, vendor: Vendor) -> Result<Option<Cues>> {
+    Cues::read(self, vendor, &Mp3Timing::read(self)?)
+  }
 
+  pub fn set_cues(&mut self, cues: &Cues, vendors: &[Vendor]) -> Result<()> {
+    let timing = Mp3Timing::read(self)?;
+    for vendor in vendors {
+      cues.write(self, *vendor, &timing)?;
+    }
     Ok(())
   }
+}
+
+impl Deref for ID3rs {
+  type Target = Tag;
+
+  fn deref(&self) -> &Tag {
+    &self.tag
+  }
+}
+
+impl DerefMut for ID3rs {
+  fn deref_mut(&mut self) -> &mut Tag {
+    &mut self.tag
+  }
+}
+
+impl Tag {
+  pub fn new() -> Tag {
+    Tag::default()
+  }
+
+  /// Parses a complete tag, starting with the ID3 header
+  pub fn parse(input: &[u8]) -> Result<Tag> {
+    let (input, header) = file_header(input).map_err(|_| "No ID3 header")?;
+    let input = input.get(..header.tag_size as usize).ok_or("Truncated tag")?;
+    Tag::parse_frames(header.version, input)
+  }
 
-  fn write_padding(&self, out: &mut File) -> Result<u64> {
-    let mut header_size = out.stream_position()? - ID3HEADER_SIZE;
-    let padding = if header_size < self.header_size {
-      debug!("Using padding");
-      self.header_size - header_size
-    } else {
-      debug!("Growing padding");
-      let modulo = (ID3HEADER_SIZE + header_size) % ID3HEADER_ALIGN;
-      (2 * ID3HEADER_ALIGN) - modulo
+  fn parse_frames(version: u8, input: &[u8]) -> Result<Tag> {
+    let (_, raw_frames) = match version {
+      3 => all_raw_frames(v23_len)(input).map_err(|_| "Frames error")?,
+      4 => all_raw_frames(v24_len)(input).map_err(|_| "Frames error")?,
+      v => Err(format!("Invalid version: {}", v))?
     };
-    out.write_all(&vec![0; padding as usize])?;
-    header_size += padding;
-    Ok(header_size)
+    let original = raw_frames.iter()
+      .filter(|(_, frame)| !matches!(frame, Frame::Padding { .. }))
+      .map(|(raw, frame)| (frame.clone(), raw.to_vec()))
+      .collect();
+    let frames = raw_frames.into_iter().map(|(_, frame)| frame).collect();
+    Ok(Tag { frames, dirty: false, version, original })
   }
 
-  fn write_id3_frames(frames: &[Frame], out: &mut File) -> Result<()> {
-    for frame in frames.iter() {
+  fn read_header(reader: &mut impl Read) -> Result<Option<Header>> {
+    let mut buffer = [0; ID3HEADER_SIZE as usize];
+    reader.read_exact(&mut buffer)?;
+    Ok(file_header(&buffer).ok().map(|(_, header)| header))
+  }
+
+  /// The ID3v2 version the tag is written as, that of the parsed tag or 4 for a new one
+  pub fn version(&self) -> u8 {
+    if self.version == 3 { 3 } else { 4 }
+  }
+
+  /// The header, frames and `padding` zero bytes of an ID3v2.`version` tag, version 3 and 4 are supported.
+  pub fn to_bytes(&self, version: u8, padding: usize) -> Result<Vec<u8>> {
+    Ok(Tag::with_header(version, self.frame_bytes(version)?, padding))
+  }
+
+  fn with_header(version: u8, frames: Vec<u8>, padding: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(ID3HEADER_SIZE as usize + frames.len() + padding);
+    out.extend_from_slice(&[b'I', b'D', b'3', version, 0, 0]);
+    out.extend(as_syncsafe((frames.len() + padding) as u32));
+    out.extend(frames);
+    out.resize(out.len() + padding, 0);
+    out
+  }
+
+  fn frame_bytes(&self, version: u8) -> Result<Vec<u8>> {
+    if version != 3 && version != 4 {
+      Err(format!("Invalid version: {}", version))?
+    }
+    let mut out = vec![];
+    self.write_id3_frames(version, &mut out)?;
+    Ok(out)
+  }
+
+  /// ID3v2.3 has no UTF-8, strings of version 3 frames are written as UTF-16 instead.
+  fn write_id3_frames(&self, version: u8, out: &mut impl Write) -> Result<()> {
+    let size = |len: usize| if version == 3 { (len as u32).to_be_bytes().to_vec() } else { as_syncsafe(len as u32) };
+    let (encoding, terminator): (&[u8], &[u8]) = if version == 3 { (b"\x01", b"\x00\x00") } else { (b"\x03", b"\x00") };
+    let encode = |text: &str| if version == 3 { utf16(text) } else { text.as_bytes().to_vec() };
+    let mut unchanged: Vec<&(Frame, Vec<u8>)> = self.original.iter().filter(|_| version == self.version).collect();
+    for frame in self.frames.iter() {
+      if let Some(index) = unchanged.iter().position(|(original, _)| original == frame) {
+        let (_, raw) = unchanged.swap_remove(index);
+        debug!("unchanged {} len {}", String::from_utf8_lossy(&raw[..4]), raw.len());
+        out.write_all(raw)?;
+        continue;
+      }
       match frame {
-        Frame::Generic { id, size, flags, data } => {
+        Frame::Generic { id, size: len, flags, data } => {
           out.write_all(id.as_ref())?;
-          let vec = as_syncsafe(*size);
-          debug!("frame {} len {}", id, size);
-          out.write_all(&vec)?;
+          debug!("frame {} len {}", id, len);
+          out.write_all(&size(*len as usize))?;
           out.write_all(&flags.to_be_bytes())?;
           out.write_all(data)?;
         }
         Frame::Text { id, size: _, flags, text } => {
-          let text: Vec<u8> = text.encode_utf16().flat_map(|w| w.to_le_bytes()).collect();
-          let len = text.len() as u32 + 3;
-          let size = as_syncsafe(len);
+          let text = utf16(text);
+          let len = text.len() + 1;
           debug!("text {} len {}", id, len);
           out.write_all(id.as_ref())?;
-          out.write_all(&size)?;
+          out.write_all(&size(len))?;
           out.write_all(&flags.to_be_bytes())?;
 
-          out.write_all(b"\x01\xff\xfe")?;
+          out.write_all(b"\x01")?;
           out.write_all(&text)?;
         }
         Frame::Comment { id, size: _, flags, language, description, value } => {
-          let len = language.len() + description.len() + value.len() + 2;
-          let size = as_syncsafe(len as u32);
+          let (description, value) = (encode(description), encode(value));
+          let len = 1 + language.len() + description.len() + terminator.len() + value.len();
           debug!("comment {} len {}", id, len);
           out.write_all(id.as_ref())?;
-          out.write_all(&size)?;
+          out.write_all(&size(len))?;
           out.write_all(&flags.to_be_bytes())?;
 
-          out.write_all(b"\x03")?;
+          out.write_all(encoding)?;
           out.write_all(language.as_bytes())?;
-          out.write_all(description.as_bytes())?;
-          out.write_all(b"\x00")?;
-          out.write_all(value.as_bytes())?;
+          out.write_all(&description)?;
+          out.write_all(terminator)?;
+          out.write_all(&value)?;
         }
         Frame::ExtendedText { id, size: _, flags, description, value } => {
-          let len = description.len() + value.len() + 2;
-          let size = as_syncsafe(len as u32);
+          let (description, value) = (encode(description), encode(value));
+          let len = 1 + description.len() + terminator.len() + value.len();
           debug!("extended {} len {}", id, len);
           out.write_all(id.as_ref())?;
-          out.write_all(&size)?;
+          out.write_all(&size(len))?;
           out.write_all(&flags.to_be_bytes())?;
 
-          out.write_all(b"\x03")?;
-          out.write_all(description.as_bytes())?;
-          out.write_all(b"\x00")?;
-          out.write_all(value.as_bytes())?;
+          out.write_all(encoding)?;
+          out.write_all(&description)?;
+          out.write_all(terminator)?;
+          out.write_all(&value)?;
         }
         Frame::Object { id, flags, mime_type, filename, description, data, .. } => {
-          let len = mime_type.len() + filename.len() + description.len() + 4 + data.len();
-          let size = as_syncsafe(len as u32);
+          let (filename, description) = (encode(filename), encode(description));
+          let len = 1 + mime_type.len() + 1 + filename.len() + description.len() + 2 * terminator.len() + data.len();
           debug!("object {} len {}", id, len);
           out.write_all(id.as_ref())?;
-          out.write_all(&size)?;
+          out.write_all(&size(len))?;
           out.write_all(&flags.to_be_bytes())?;
 
-          out.write_all(b"\x03")?;
+          out.write_all(encoding)?;
           out.write_all(mime_type.as_bytes())?;
           out.write_all(b"\x00")?;
-          out.write_all(filename.as_bytes())?;
-          out.write_all(b"\x00")?;
-          out.write_all(description.as_bytes())?;
-          out.write_all(b"\x00")?;
+          out.write_all(&filename)?;
+          out.write_all(terminator)?;
+          out.write_all(&description)?;
+          out.write_all(terminator)?;
           out.write_all(data)?;
         }
         Frame::Picture { id, flags, kind, mime_type, description, data, .. } => {
-          let len = mime_type.len() + description.len() + 4 + data.len();
-          let size = as_syncsafe(len as u32);
+          let description = encode(description);
+          let len = 1 + mime_type.len() + 1 + 1 + description.len() + terminator.len() + data.len();
           debug!("picture {} len {}", id, len);
           out.write_all(id.as_ref())?;
-          out.write_all(&size)?;
+          out.write_all(&size(len))?;
           out.write_all(&flags.to_be_bytes())?;
 
-          out.write_all(b"\x03")?;
+          out.write_all(encoding)?;
           out.write_all(mime_type.as_bytes())?;
           out.write_all(b"\x00")?;
           out.write_all(&kind.to_be_bytes())?;
-          out.write_all(description.as_bytes())?;
-          out.write_all(b"\x00")?;
+          out.write_all(&description)?;
+          out.write_all(terminator)?;
           out.write_all(data)?;
         }
         Frame::Popularity { id, flags, email, rating, .. } => {
           let len = email.len() + 2; // NULL byte and rating
-          let size = as_syncsafe(len as u32);
-          debug!("picture {} len {}", id, len);
+          debug!("popularity {} len {}", id, len);
           out.write_all(id.as_ref())?;
-          out.write_all(&size)?;
+          out.write_all(&size(len))?;
           out.write_all(&flags.to_be_bytes())?;
           out.write_all(email.as_bytes())?;
           out.write_all(b"\x00")?;
@@ -382,6 +620,15 @@ impl ID3rs {
     })
   }
 
+  /// Data of the private frame for `owner`, without the owner identifier
+  pub fn private(&self, owner: &str) -> Option<&[u8]> {
+    self.frames.iter().find_map(|f| match f {
+      Frame::Generic { id, data, .. } if id == PRIVATE_TAG => data.strip_prefix(owner.as_bytes())
+        .and_then(|data| data.strip_prefix(b"\x00")),
+      _ => None
+    })
+  }
+
   pub fn extended_text(&self, name: &str) -> Option<&str> {
     self.extended_text_frame(name).and_then(|f| match f {
       Frame::ExtendedText { value, .. } => Some(value.as_str()),
@@ -397,13 +644,25 @@ impl ID3rs {
   }
 
   pub fn attached_picture(&self, kind: Picture) -> Option<&Frame> {
-    let kind = kind as u8;
+    let kind = u8::from(kind);
     self.frames.iter().find(|f| match f {
       Frame::Picture { kind: kind_, .. } => &kind == kind_,
       _ => false
     })
   }
 
+  pub fn attached_picture_by_description(&self, kind: Picture, text: &str) -> Option<&Frame> {
+    let kind = u8::from(kind);
+    self.frames.iter().find(|f| match f {
+      Frame::Picture { kind: kind_, description, .. } => &kind == kind_ && description == text,
+      _ => false
+    })
+  }
+
+  pub fn pictures(&self) -> Vec<&Frame> {
+    self.frames.iter().filter(|f| matches!(f, Frame::Picture { .. })).collect()
+  }
+
   pub fn title(&self) -> Option<&str> {
     self.text(TITLE_TAG)
   }
@@ -413,7 +672,7 @@ impl ID3rs {
   }
 
   pub fn album(&self) -> Option<&str> {
-    self.text(ARTIST_TAG)
+    self.text(ALBUM_TAG)
   }
 
   pub fn artist(&self) -> Option<&str> {
@@ -426,6 +685,28 @@ impl ID3rs {
 
   pub fn key(&self) -> Option<&str> { self.text(KEY_TAG) }
 
+  pub fn bpm(&self) -> Option<f64> { self.text(BPM_TAG).and_then(|bpm| bpm.trim().parse().ok()) }
+
+  /// `TLEN` in milliseconds
+  pub fn length(&self) -> Option<u64> { self.text(LENGTH_TAG).and_then(|length| length.trim().parse().ok()) }
+
+  /// Parses `TKEY`, falling back to the extended text frames Mixed In Key and others write.
+  pub fn musical_key(&self) -> Option<MusicalKey> {
+    self.key().and_then(MusicalKey::parse)
+      .or_else(|| self.extended_key_frame().and_then(|f| match f {
+        Frame::ExtendedText { value, .. } => MusicalKey::parse(value),
+        _ => None
+      }))
+  }
+
+  fn extended_key_frame(&self) -> Option<&Frame> {
+    self.frames.iter().find(|f| match f {
+      Frame::ExtendedText { description, .. } =>
+        EXTENDED_KEY_NAMES.iter().any(|name| description.eq_ignore_ascii_case(name)),
+      _ => false
+    })
+  }
+
   pub fn grouping(&self) -> Option<&str> { self.text(GROUPING_TAG) }
 
   pub fn release(&self) -> Option<&str> { self.text(RELEASE_TAG) }
@@ -467,6 +748,32 @@ impl ID3rs {
 
   pub fn set_key(&mut self, text: &str) { self.set_text(KEY_TAG, text); }
 
+  /// `TBPM` holds whole beats per minute
+  pub fn set_bpm(&mut self, bpm: f64) { self.set_text(BPM_TAG, &bpm.round().to_string()); }
+
+  pub fn set_length(&mut self, length_ms: u64) { self.set_text(LENGTH_TAG, &length_ms.to_string()); }
+
+  pub fn set_musical_key(&mut self, key: MusicalKey, notation: KeyNotation) {
+    self.set_key(&key.to_notation(notation));
+  }
+
+  /// Rewrites `TKEY` and any extended key frame in the given notation, returns the key found.
+  pub fn normalize_key(&mut self, notation: KeyNotation) -> Option<MusicalKey> {
+    let key = self.musical_key()?;
+    let text = key.to_notation(notation);
+    if self.key() != Some(text.as_str()) {
+      self.set_key(&text);
+    }
+    let extended = self.extended_key_frame().and_then(|f| match f {
+      Frame::ExtendedText { description, value, .. } if value != &text => Some(description.clone()),
+      _ => None
+    });
+    if let Some(name) = extended {
+      self.set_extended_text(&name, &text);
+    }
+    Some(key)
+  }
+
   pub fn set_genre(&mut self, text: &str) {
     self.set_text(GENRE_TAG, text);
   }
@@ -476,14 +783,12 @@ impl ID3rs {
   }
 
   pub fn set_object(&mut self, name: &str, mime_type: &str, description: &str, data: &[u8]) {
-    if let Some(index) = self.frames.iter().position(|frame|
+    let index = self.frames.iter().position(|frame|
       match frame {
         Frame::Object { id, filename, .. } => id == OBJECT_TAG && filename == name,
         _ => false
-      }) {
-      self.frames.remove(index);
-    }
-    self.push_new_frame(Frame::Object {
+      });
+    self.put_frame(index, Frame::Object {
       id: OBJECT_TAG.to_string(),
       size: 0,
       flags: 0,
@@ -494,48 +799,75 @@ impl ID3rs {
     })
   }
 
+  /// Mixed In Key and Serato leave the filename empty and identify objects by description.
+  pub fn set_object_by_description(&mut self, text: &str, mime_type: &str, data: &[u8]) {
+    let index = self.frames.iter().position(|frame|
+      match frame {
+        Frame::Object { id, description, .. } => id == OBJECT_TAG && description == text,
+        _ => false
+      });
+    self.put_frame(index, Frame::Object {
+      id: OBJECT_TAG.to_string(),
+      size: 0,
+      flags: 0,
+      filename: "".to_string(),
+      description: text.to_string(),
+      mime_type: mime_type.to_string(),
+      data: Vec::from(data),
+    })
+  }
+
+  pub fn set_private(&mut self, owner: &str, value: &[u8]) {
+    let index = self.frames.iter().position(|frame|
+      match frame {
+        Frame::Generic { id, data, .. } => id == PRIVATE_TAG && data.strip_prefix(owner.as_bytes())
+          .is_some_and(|data| data.starts_with(b"\x00")),
+        _ => false
+      });
+    let mut data = Vec::with_capacity(owner.len() + 1 + value.len());
+    data.extend_from_slice(owner.as_bytes());
+    data.push(0);
+    data.extend_from_slice(value);
+    self.put_frame(index, Frame::Generic { id: PRIVATE_TAG.to_string(), size: data.len() as u32, flags: 0, data })
+  }
+
   pub fn clear_popularities(&mut self) {
-    self.frames.retain(|frame| match frame {
-      Frame::Popularity { .. } => false,
-      _ => true
-    });
+    self.frames.retain(|frame| !matches!(frame, Frame::Popularity { .. }));
   }
 
   pub fn set_popularity(&mut self, author: &str, rating: u8) {
     assert!(rating <= 5);
-    if let Some(index) = self.frames.iter().position(|frame|
+    let index = self.frames.iter().position(|frame|
       match frame {
         Frame::Popularity { id, email, .. } => id == POPULARITY_TAG && email == author,
         _ => false
-      }) {
-      self.frames.remove(index);
-    }
+      });
     let adjusted = rating * 51;
-    self.push_new_frame(Frame::Popularity { id: POPULARITY_TAG.to_string(), size: 0, flags: 0, email: author.to_string(), rating: adjusted });
+    self.put_frame(index, Frame::Popularity { id: POPULARITY_TAG.to_string(), size: 0, flags: 0, email: author.to_string(), rating: adjusted });
   }
 
   pub fn set_text(&mut self, id3: &str, change: &str) {
-    if let Some(index) = self.frames.iter().position(|frame|
+    let index = self.frames.iter().position(|frame|
       match frame {
         Frame::Text { id, .. } => id == id3,
         _ => false
-      }) {
-      self.frames.remove(index);
-    }
-    self.push_new_frame(Frame::Text { id: id3.to_string(), size: 0, flags: 0, text: change.to_string() });
+      });
+    self.put_frame(index, Frame::Text { id: id3.to_string(), size: 0, flags: 0, text: change.to_string() });
   }
 
-  fn push_new_frame(&mut self, frames: Frame) {
-    self.frames.push(frames);
+  /// Puts `frame` in the place of the frame at `index`, or after all frames
+  fn put_frame(&mut self, index: Option<usize>, frame: Frame) {
+    match index {
+      Some(index) => self.frames[index] = frame,
+      None => self.frames.push(frame),
+    }
     self.dirty = true
   }
 
   pub fn set_comment(&mut self, description: &str, value: &str) {
-    if let Some(index) = self.frames.iter()
-      .position(|frame| matches!(frame, Frame::Comment { .. })) {
-      self.frames.remove(index);
-    }
-    self.push_new_frame(Frame::Comment {
+    let index = self.frames.iter()
+      .position(|frame| matches!(frame, Frame::Comment { .. }));
+    self.put_frame(index, Frame::Comment {
       id: COMMENT_TAG.to_string(),
       size: 0,
       flags: 0,
@@ -546,44 +878,88 @@ impl ID3rs {
   }
 
   pub fn set_extended_text(&mut self, name: &str, value: &str) {
-    if let Some(index) = self.frames.iter().position(|frame|
+    let index = self.frames.iter().position(|frame|
       match frame {
         Frame::ExtendedText { description, .. } => description == name,
         _ => false
-      }) {
-      self.frames.remove(index);
-    }
-    self.push_new_frame(Frame::ExtendedText { id: EXTENDED_TAG.to_string(), size: 0, flags: 0, description: name.to_string(), value: value.to_string() });
+      });
+    self.put_frame(index, Frame::ExtendedText { id: EXTENDED_TAG.to_string(), size: 0, flags: 0, description: name.to_string(), value: value.to_string() });
   }
 
+  /// Replaces the picture of the same kind and description, other pictures of that kind stay.
   pub fn set_attached_picture(&mut self, kind: Picture, mime_type: &str, description: &str, data: &[u8]) {
-    let kind = kind as u8;
-    if let Some(index) = self.frames.iter().position(|frame|
-      match frame {
-        Frame::Picture { kind: kind_, .. } => kind_ == &kind,
-        _ => false
-      }) {
-      self.frames.remove(index);
+    let kind = u8::from(kind);
+    let index = self.frames.iter().position(|frame|
+      matches!(frame, Frame::Picture { kind: kind_, description: text, .. } if kind_ == &kind && text == description));
+    self.put_frame(index, Frame::Picture { id: PICTURE_TAG.to_string(), size: 0, flags: 0, kind, mime_type: mime_type.to_string(), description: description.to_string(), data: Vec::from(data) });
+  }
+
+  /// Like `set_attached_picture`, with the MIME type recognised from the image data
+  pub fn set_picture(&mut self, kind: Picture, description: &str, data: &[u8]) -> Result<()> {
+    let mime_type = picture::mime_type(data).ok_or("Unknown image format")?;
+    self.set_attached_picture(kind, mime_type, description, data);
+    Ok(())
+  }
+
+  pub fn remove_attached_picture(&mut self, kind: Picture, text: &str) -> bool {
+    let kind = u8::from(kind);
+    let count = self.frames.len();
+    self.frames.retain(|frame| !matches!(frame, Frame::Picture { kind: kind_, description, .. } if kind_ == &kind && description == text));
+    self.dirty |= count != self.frames.len();
+    count != self.frames.len()
+  }
+
+  /// Re-encodes the front cover as `format` within `max_dim` pixels, when it is larger or of another format.
+  /// Returns whether the cover was changed.
+  #[cfg(feature = "image")]
+  pub fn normalize_cover(&mut self, max_dim: u32, format: picture::ImageFormat, quality: u8) -> Result<bool> {
+    let kind = u8::from(Picture::FrontCover);
+    let Some(Frame::Picture { mime_type, data, .. }) = self.frames.iter_mut()
+      .find(|frame| matches!(frame, Frame::Picture { kind: kind_, .. } if kind_ == &kind)) else {
+      return Ok(false);
+    };
+    let info = picture::image_info(data)?;
+    if info.format == format && info.width <= max_dim && info.height <= max_dim {
+      return Ok(false);
     }
-    self.push_new_frame(Frame::Picture { id: PICTURE_TAG.to_string(), size: 0, flags: 0, kind, mime_type: mime_type.to_string(), description: description.to_string(), data: Vec::from(data) });
+    *data = picture::normalize(data, max_dim, format, quality)?;
+    *mime_type = format.mime_type().to_string();
+    self.dirty = true;
+    Ok(true)
+  }
+
+  /// Removes all pictures of `kind`, returns how many there were
+  pub fn remove_attached_pictures(&mut self, kind: Picture) -> usize {
+    let kind = u8::from(kind);
+    let count = self.frames.len();
+    self.frames.retain(|frame| !matches!(frame, Frame::Picture { kind: kind_, .. } if kind_ == &kind));
+    self.dirty |= count != self.frames.len();
+    count - self.frames.len()
   }
 }
 
+fn padding_for(header_size: u64, existing: u64) -> u64 {
+  if header_size < existing {
+    debug!("Using padding");
+    existing - header_size
+  } else {
+    debug!("Growing padding");
+    let modulo = (ID3HEADER_SIZE + header_size) % ID3HEADER_ALIGN;
+    (2 * ID3HEADER_ALIGN) - modulo
+  }
+}
+
+/// UTF-16LE with byte order mark
+fn utf16(text: &str) -> Vec<u8> {
+  b"\xff\xfe".iter().copied().chain(text.encode_utf16().flat_map(|w| w.to_le_bytes())).collect()
+}
+
 pub fn log_init() {
   let _ = env_logger::builder().is_test(true)
     .filter_level(LevelFilter::Debug)
     .try_init();
 }
 
-pub fn mpck(filepath: &str) -> String {
-  let output = Command::new("mpck")
-    .arg(filepath)
-    .output()
-    .unwrap_or_else(|_| panic!("Failed `mpck {}`", filepath));
-
-  String::from_utf8(output.stdout).unwrap().replace(filepath, "")
-}
-
 #[allow(clippy::permissions_set_readonly_false)]
 pub fn make_rwcopy(rofile: &str, rwfile: &str) -> Result<()> {
   fs::copy(rofile, rwfile)?;
@@ -38,6 +38,10 @@ fn main() -> Result<()> {
       let size = id3.header_size + ID3HEADER_SIZE;
       println!(" Offset: {:#06X} {}", size, size);
       check_first_frame(&id3)?;
+      let audio = id3.audio_info()?;
+      let seconds = (audio.duration_ms / 1000.0).round() as u64;
+      println!(" Length: {}:{:02} ({:.0} ms)", seconds / 60, seconds % 60, audio.duration_ms);
+      println!("  Audio: {:?} {:.0} kbps {} Hz", audio.bitrate_mode, audio.average_kbps, audio.sample_rate);
     }
     _ => unreachable!(),
   }
@@ -0,0 +1,181 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Deserialize;
+
+use crate::musical_key::{KeyNotation, MusicalKey};
+use crate::{Frame, Result, Tag};
+
+// Mixed In Key stores its analysis as base64 encoded JSON in GEOB frames identified by description
+
+pub static CUE_POINTS_OBJECT: &str = "CuePoints";
+pub static KEY_OBJECT: &str = "Key";
+pub static ENERGY_OBJECT: &str = "Energy";
+pub static ENERGY_LEVEL: &str = "EnergyLevel";
+
+const MIME_TYPE: &str = "application/json";
+const SOURCE: &str = "mixedinkey";
+const LINE_LENGTH: usize = 72;
+
+const CUE_POINTS_ALGORITHM: u32 = 14;
+const KEY_ALGORITHM: u32 = 94;
+const ENERGY_ALGORITHM: u32 = 13;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+  pub name: String,
+  pub time_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MixedInKey {
+  pub energy: Option<u8>,
+  pub key: Option<MusicalKey>,
+  pub cues: Vec<Cue>,
+}
+
+#[derive(Deserialize)]
+struct CuePointsJson {
+  cues: Vec<CueJson>,
+}
+
+#[derive(Deserialize)]
+struct CueJson {
+  name: String,
+  time: f64,
+}
+
+#[derive(Deserialize)]
+struct KeyJson {
+  key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnergyJson {
+  energy_level: u8,
+}
+
+impl MixedInKey {
+  /// Prefers the GEOB analysis objects and falls back to `EnergyLevel` and `TKEY`.
+  pub fn read(tag: &Tag) -> Result<MixedInKey> {
+    let energy = match object_data(tag, ENERGY_OBJECT) {
+      Some(data) => Some(decode_energy(data)?),
+      None => tag.extended_text(ENERGY_LEVEL).and_then(|level| level.trim().parse().ok())
+    };
+    let key = match object_data(tag, KEY_OBJECT) {
+      Some(data) => Some(decode_key(data)?),
+      None => tag.musical_key()
+    };
+    let cues = match object_data(tag, CUE_POINTS_OBJECT) {
+      Some(data) => decode_cue_points(data)?,
+      None => vec![]
+    };
+    Ok(MixedInKey { energy, key, cues })
+  }
+
+  pub fn write(&self, tag: &mut Tag) -> Result<()> {
+    if let Some(energy) = self.energy {
+      tag.set_extended_text(ENERGY_LEVEL, &energy.to_string());
+      tag.set_object_by_description(ENERGY_OBJECT, MIME_TYPE, &encode_energy(energy));
+    }
+    if let Some(key) = self.key {
+      tag.set_musical_key(key, KeyNotation::Standard);
+      tag.set_object_by_description(KEY_OBJECT, MIME_TYPE, &encode_key(key));
+    }
+    if !self.cues.is_empty() || tag.object_by_description(CUE_POINTS_OBJECT).is_some() {
+      tag.set_object_by_description(CUE_POINTS_OBJECT, MIME_TYPE, &encode_cue_points(&self.cues)?);
+    }
+    Ok(())
+  }
+}
+
+fn object_data<'a>(tag: &'a Tag, description: &str) -> Option<&'a [u8]> {
+  tag.object_by_description(description).and_then(|f| match f {
+    Frame::Object { data, .. } => Some(data.as_slice()),
+    _ => None
+  })
+}
+
+pub fn decode_cue_points(data: &[u8]) -> Result<Vec<Cue>> {
+  let json: CuePointsJson = serde_json::from_slice(&decode_base64(data)?)?;
+  Ok(json.cues.into_iter().map(|cue| Cue { name: cue.name, time_ms: cue.time }).collect())
+}
+
+/// Fails on a cue time that is not finite, JSON has no NaN or infinity
+pub fn encode_cue_points(cues: &[Cue]) -> Result<Vec<u8>> {
+  let cues = cues.iter()
+    .map(|cue| match cue.time_ms.is_finite() {
+      true => Ok(format!(r#"{{"name":{},"time":{}}}"#, json_string(&cue.name), format_number(cue.time_ms))),
+      false => Err(format!("Invalid time {} for cue {:?}", cue.time_ms, cue.name)),
+    })
+    .collect::<std::result::Result<Vec<_>, _>>()?;
+  Ok(encode_base64(&format!(r#"{{"algorithm":{},"cues":[{}],"source":"{}"}}"#, CUE_POINTS_ALGORITHM, cues.join(","), SOURCE)))
+}
+
+pub fn decode_key(data: &[u8]) -> Result<MusicalKey> {
+  let json: KeyJson = serde_json::from_slice(&decode_base64(data)?)?;
+  Ok(json.key.parse()?)
+}
+
+pub fn encode_key(key: MusicalKey) -> Vec<u8> {
+  encode_base64(&format!(r#"{{"algorithm":{},"key":{},"source":"{}"}}"#, KEY_ALGORITHM, json_string(&key.standard()), SOURCE))
+}
+
+pub fn decode_energy(data: &[u8]) -> Result<u8> {
+  let json: EnergyJson = serde_json::from_slice(&decode_base64(data)?)?;
+  Ok(json.energy_level)
+}
+
+pub fn encode_energy(level: u8) -> Vec<u8> {
+  encode_base64(&format!(r#"{{"algorithm":{},"energyLevel":{},"source":"{}"}}"#, ENERGY_ALGORITHM, level, SOURCE))
+}
+
+fn decode_base64(data: &[u8]) -> Result<Vec<u8>> {
+  let text: Vec<u8> = data.iter().copied()
+    .filter(|b| !b.is_ascii_whitespace() && *b != 0)
+    .collect();
+  Ok(STANDARD.decode(text)?)
+}
+
+/// Lines of 72 characters, each terminated by a newline, followed by a closing newline
+fn encode_base64(json: &str) -> Vec<u8> {
+  let encoded = STANDARD.encode(json);
+  let mut result = Vec::with_capacity(encoded.len() + encoded.len() / LINE_LENGTH + 2);
+  for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+    result.extend_from_slice(line);
+    if line.len() == LINE_LENGTH {
+      result.push(b'\n');
+    }
+  }
+  result.push(b'\n');
+  result
+}
+
+fn json_string(text: &str) -> String {
+  serde_json::to_string(text).unwrap()
+}
+
+/// Formats a finite `value` like `%.17g`, which is how Mixed In Key writes its timestamps
+fn format_number(value: f64) -> String {
+  let scientific = format!("{:.16e}", value);
+  let (mantissa, exponent) = scientific.split_once('e').unwrap();
+  let exponent: i32 = exponent.parse().unwrap();
+  let sign = if mantissa.starts_with('-') { "-" } else { "" };
+  let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+
+  if !(-4..17).contains(&exponent) {
+    let (first, fraction) = digits.split_at(1);
+    let fraction = fraction.trim_end_matches('0');
+    let fraction = if fraction.is_empty() { "".to_string() } else { format!(".{}", fraction) };
+    return format!("{}{}{}e{}{:02}", sign, first, fraction, if exponent < 0 { '-' } else { '+' }, exponent.abs());
+  }
+
+  let text = if exponent < 0 {
+    format!("0.{}{}", "0".repeat((-exponent - 1) as usize), digits)
+  } else {
+    let (whole, fraction) = digits.split_at(exponent as usize + 1);
+    format!("{}.{}", whole, fraction)
+  };
+  let text = text.trim_end_matches('0').trim_end_matches('.');
+  format!("{}{}", sign, text)
+}
@@ -4,6 +4,8 @@ use nom::bits::{bits, streaming::take};
 use nom::bytes::streaming::take_until;
 use nom::{error, number, AsBytes, IResult};
 
+use crate::vbr_header::VbrHeader;
+
 #[derive(Debug, PartialEq)]
 pub struct FrameHeader {
   pub version: Version,
@@ -12,10 +14,15 @@ pub struct FrameHeader {
   pub bitrate: u32,
   pub frequency: u32,
   pub padding: u8,
+  pub private: bool,
+  pub channel_mode: ChannelMode,
+  pub copyright: bool,
+  pub original: bool,
+  pub emphasis: Emphasis,
   pub data: Vec<u8>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Version {
   Version25,
   Version2,
@@ -34,7 +41,7 @@ impl From<u8> for Version {
   }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Layer {
   Layer1,
   Layer2,
@@ -59,6 +66,50 @@ pub enum Protection {
   Unprotected,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum ChannelMode {
+  Stereo,
+  /// Layer I and II only use intensity stereo, the mode extension selects the bands it applies to
+  JointStereo { mid_side: bool, intensity: bool },
+  DualChannel,
+  Mono,
+}
+
+impl ChannelMode {
+  fn from(layer: &Layer, channel: u8, mode: u8) -> ChannelMode {
+    match channel {
+      0b00 => ChannelMode::Stereo,
+      0b01 => match layer {
+        Layer::Layer3 => ChannelMode::JointStereo { mid_side: mode & 0b10 != 0, intensity: mode & 0b01 != 0 },
+        _ => ChannelMode::JointStereo { mid_side: false, intensity: true },
+      },
+      0b10 => ChannelMode::DualChannel,
+      _ => ChannelMode::Mono,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Emphasis {
+  None,
+  /// 50/15 microseconds
+  Ms5015,
+  Reserved,
+  /// CCITT J.17
+  CcittJ17,
+}
+
+impl From<u8> for Emphasis {
+  fn from(emphasis: u8) -> Emphasis {
+    match emphasis {
+      0b00 => Emphasis::None,
+      0b01 => Emphasis::Ms5015,
+      0b10 => Emphasis::Reserved,
+      _ => Emphasis::CcittJ17,
+    }
+  }
+}
+
 impl From<u8> for Protection {
   fn from(version: u8) -> Protection {
     match version {
@@ -68,53 +119,159 @@ impl From<u8> for Protection {
   }
 }
 
+static BITRATES_V1_L1: [u32; 14] = [32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448];
+static BITRATES_V1_L2: [u32; 14] = [32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384];
+static BITRATES_V1_L3: [u32; 14] = [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+static BITRATES_V2_L1: [u32; 14] = [32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256];
+static BITRATES_V2_L23: [u32; 14] = [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+// Bits of the Layer II bit allocation per subband, ISO 11172-3 tables B.2a to B.2d and ISO 13818-3 table B.1
+static ALLOCATION_A: [usize; 27] = [4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 2, 2, 2, 2];
+static ALLOCATION_B: [usize; 30] = [4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2];
+static ALLOCATION_C: [usize; 8] = [4, 4, 3, 3, 3, 3, 3, 3];
+static ALLOCATION_D: [usize; 12] = [4, 4, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3];
+static ALLOCATION_LSF: [usize; 30] = [4, 4, 4, 4, 3, 3, 3, 3, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2];
+
 impl FrameHeader {
+  /// 0 for a header with a reserved layer or no sampling frequency, which the parsers never return
   pub fn frame_size(&self) -> u32 {
-    Self::frame_sizeof(&self.layer, self.bitrate, self.frequency, self.padding)
+    Self::frame_sizeof(&self.version, &self.layer, self.bitrate, self.frequency, self.padding).unwrap_or(0)
   }
 
-  fn bitrate_to_kbps(version: &Version, layer: &Layer, bitrate: u8) -> u32 {
-    match (version, layer) {
-      (Version::Version1, Layer::Layer3) => match bitrate {
-        0b0001 => 32,
-        0b0010 => 40,
-        0b0011 => 48,
-        0b0100 => 56,
-        0b0101 => 64,
-        0b0110 => 80,
-        0b0111 => 96,
-        0b1000 => 112,
-        0b1001 => 128,
-        0b1010 => 160,
-        0b1011 => 192,
-        0b1100 => 224,
-        0b1101 => 256,
-        0b1110 => 320,
-        _ => 0,
-      },
-      (_, _) => 0,
+  pub fn samples_per_frame(&self) -> u32 {
+    Self::samples_per_frameof(&self.version, &self.layer)
+  }
+
+  /// The Xing/Info or VBRI header, only the first frame of a file has one
+  pub fn vbr_header(&self) -> Option<VbrHeader> {
+    VbrHeader::parse(self)
+  }
+
+  /// Bytes of Layer III side information following the header and CRC
+  pub fn side_info_size(&self) -> usize {
+    match (self.version == Version::Version1, self.channel_mode == ChannelMode::Mono) {
+      (true, false) => 32,
+      (true, true) | (false, false) => 17,
+      (false, true) => 9,
+    }
+  }
+
+  /// Whether the CRC-16 following the header matches, `None` for an unprotected or incomplete frame
+  pub fn crc_ok(&self) -> Option<bool> {
+    if self.crc != Protection::Crc {
+      return None;
+    }
+    let stored = u16::from_be_bytes([*self.data.get(4)?, *self.data.get(5)?]);
+    let bits = self.protected_bits()?;
+    let protected = self.data.get(6..6 + bits.div_ceil(8))?;
+    let crc = crc16(0xffff, &self.data[2..4], 16);
+    Some(crc16(crc, protected, bits) == stored)
+  }
+
+  /// Bits after the CRC that it covers: the bit allocation, plus the scale factor selection for Layer II,
+  /// or the side information for Layer III
+  fn protected_bits(&self) -> Option<usize> {
+    let channels = if self.channel_mode == ChannelMode::Mono { 1 } else { 2 };
+    // subbands from the bound on share the allocation of both channels
+    let bound = |subbands: usize| match self.channel_mode {
+      ChannelMode::JointStereo { .. } => ((self.data[3] >> 4 & 0b11) as usize + 1) * 4,
+      _ => subbands,
+    }.min(subbands);
+    match self.layer {
+      Layer::Layer1 => Some(4 * (bound(32) * channels + 32 - bound(32))),
+      Layer::Layer2 => {
+        let allocation = self.layer2_allocation(channels as u32);
+        let bound = bound(allocation.len());
+        let data = self.data.get(6..)?;
+        let (mut position, mut selections) = (0, 0);
+        for (subband, bits) in allocation.iter().enumerate() {
+          let shared = subband >= bound;
+          for _ in 0..if shared { 1 } else { channels } {
+            if read_bits(data, position, *bits)? != 0 {
+              selections += if shared { channels } else { 1 };
+            }
+            position += bits;
+          }
+        }
+        Some(position + 2 * selections)
+      }
+      Layer::Layer3 => Some(8 * self.side_info_size()),
+      Layer::Reserved => None,
     }
   }
 
-  fn sampling_to_hz(version: &Version, sampling: u8) -> u32 {
+  /// Depends on the bitrate per channel and the sample rate
+  fn layer2_allocation(&self, channels: u32) -> &'static [usize] {
+    let bitrate = self.bitrate / channels;
+    match (self.version == Version::Version1, self.frequency, bitrate) {
+      (false, _, _) => &ALLOCATION_LSF,
+      (true, 48000, 56..) | (true, _, 56..=80) => &ALLOCATION_A,
+      (true, 44100 | 32000, 96..) => &ALLOCATION_B,
+      (true, 48000 | 44100, _) => &ALLOCATION_C,
+      (true, _, _) => &ALLOCATION_D,
+    }
+  }
+
+  /// Playing time of the frame
+  pub fn duration_ms(&self) -> f64 {
+    self.samples_per_frame() as f64 * 1000.0 / self.frequency as f64
+  }
+
+  /// Kilobits per second, `None` for free format (index 0), the invalid index 15 or a reserved version or layer
+  pub fn bitrate_to_kbps(version: &Version, layer: &Layer, bitrate: u8) -> Option<u32> {
+    let table = match (version, layer) {
+      (Version::Version1, Layer::Layer1) => &BITRATES_V1_L1,
+      (Version::Version1, Layer::Layer2) => &BITRATES_V1_L2,
+      (Version::Version1, Layer::Layer3) => &BITRATES_V1_L3,
+      (Version::Version2 | Version::Version25, Layer::Layer1) => &BITRATES_V2_L1,
+      (Version::Version2 | Version::Version25, Layer::Layer2 | Layer::Layer3) => &BITRATES_V2_L23,
+      (_, _) => return None,
+    };
+    table.get((bitrate as usize).checked_sub(1)?).copied()
+  }
+
+  /// Sample rate in Hz, `None` for the reserved index 3 or version
+  pub fn sampling_to_hz(version: &Version, sampling: u8) -> Option<u32> {
+    let hz = [44100, 48000, 32000].get(sampling as usize)?;
     match version {
-      Version::Version1 => match sampling {
-        0b0000 => 44100,
-        0b0001 => 48000,
-        0b0010 => 32000,
-        _ => 0,
-      },
-      _ => 0,
+      Version::Version1 => Some(*hz),
+      Version::Version2 => Some(hz / 2),
+      Version::Version25 => Some(hz / 4),
+      Version::Reserved => None,
     }
   }
 
-  pub fn frame_sizeof(layer: &Layer, bitrate: u32, frequency: u32, padding: u8) -> u32 {
-    match layer {
-      Layer::Layer1 => 12 * bitrate / frequency * 4,
-      Layer::Layer2 | Layer::Layer3 => 144000 * bitrate / frequency + padding as u32,
-      Layer::Reserved => panic!("Invalid layer"),
+  pub fn samples_per_frameof(version: &Version, layer: &Layer) -> u32 {
+    match (version, layer) {
+      (_, Layer::Layer1) => 384,
+      (Version::Version2 | Version::Version25, Layer::Layer3) => 576,
+      (_, _) => 1152,
     }
   }
+
+  /// Layer I counts in slots of 4 bytes, the other layers in bytes. The size includes the CRC of a protected frame.
+  pub fn frame_sizeof(version: &Version, layer: &Layer, bitrate: u32, frequency: u32, padding: u8) -> Option<u32> {
+    let slot = match layer {
+      Layer::Layer1 => 4,
+      Layer::Layer2 | Layer::Layer3 => 1,
+      Layer::Reserved => return None,
+    };
+    let slots = Self::samples_per_frameof(version, layer) / 8 / slot;
+    Some(((slots * bitrate * 1000).checked_div(frequency)? + padding as u32) * slot)
+  }
+}
+
+/// Big endian `bits` from `position` in `data`
+fn read_bits(data: &[u8], position: usize, bits: usize) -> Option<u32> {
+  (position..position + bits).try_fold(0, |value, bit| Some(value << 1 | (*data.get(bit / 8)? >> (7 - bit % 8) & 1) as u32))
+}
+
+/// CRC-16 with polynomial 0x8005 over the first `bits` of `data`, as in ISO 11172-3
+fn crc16(crc: u16, data: &[u8], bits: usize) -> u16 {
+  (0..bits).fold(crc, |crc, bit| {
+    let set = (data[bit / 8] >> (7 - bit % 8) & 1) as u16;
+    if (crc >> 15 ^ set) & 1 == 1 { crc << 1 ^ 0x8005 } else { crc << 1 }
+  })
 }
 
 // http://id3lib.sourceforge.net/id3/mp3frame.html and http://www.mp3-tech.org/programmer/frame_header.html
@@ -124,8 +281,15 @@ pub fn frame_sync(input: &[u8]) -> IResult<&[u8], ()> {
   Ok((input, ()))
 }
 
-#[allow(dead_code, unused)]
 pub fn frame_header(input: &[u8]) -> IResult<&[u8], FrameHeader> {
+  let (rest, mut frame) = frame_info(input)?;
+  let end = input.len() - rest.len();
+  frame.data = input[end - frame.frame_size() as usize..end].to_vec();
+  Ok((rest, frame))
+}
+
+/// Like `frame_header`, but leaves `data` empty instead of copying the frame
+pub fn frame_info(input: &[u8]) -> IResult<&[u8], FrameHeader> {
   let (input, _) = take_until(b"\xff".as_bytes())(input)?;
   let start = input;
   let (_input, word) = number::streaming::be_u16(input)?;
@@ -137,22 +301,28 @@ pub fn frame_header(input: &[u8]) -> IResult<&[u8], FrameHeader> {
   let (input, _) = nom::bytes::streaming::take(1u32)(input)?; // skip 0xff
   let (input, (version_u8, layer_u8, crc)) = bits(frame_header_layer)(input)?;
   let (input, (bitrate_u8, sampling_u8, padding, private)) = bits(frame_header_bitrate)(input)?;
-  let (input, (channel, mode, copyright, original, emphasis)) = bits(frame_header_mode)(input)?;
+  let (_, (channel, mode, copyright, original, emphasis)) = bits(frame_header_mode)(input)?;
 
   let version = Version::from(version_u8);
   let layer = Layer::from(layer_u8);
-  let bitrate = FrameHeader::bitrate_to_kbps(&version, &layer, bitrate_u8);
-  let frequency = FrameHeader::sampling_to_hz(&version, sampling_u8);
-  let size = FrameHeader::frame_sizeof(&layer, bitrate, frequency, padding);
-  let (input, data) = nom::bytes::streaming::take(size)(start)?;
+  let invalid = || nom::Err::Error(error::Error::new(start, error::ErrorKind::Verify));
+  let bitrate = FrameHeader::bitrate_to_kbps(&version, &layer, bitrate_u8).ok_or_else(invalid)?;
+  let frequency = FrameHeader::sampling_to_hz(&version, sampling_u8).ok_or_else(invalid)?;
+  let size = FrameHeader::frame_sizeof(&version, &layer, bitrate, frequency, padding).ok_or_else(invalid)?;
+  let (input, _) = nom::bytes::streaming::take(size)(start)?;
   let frame = FrameHeader {
+    channel_mode: ChannelMode::from(&layer, channel, mode),
     version,
     layer,
     crc: Protection::from(crc),
     bitrate,
     frequency,
     padding,
-    data: data.to_vec(),
+    private: private == 1,
+    copyright: copyright == 1,
+    original: original == 1,
+    emphasis: Emphasis::from(emphasis),
+    data: Vec::new(),
   };
 
   Ok((input, frame))
@@ -1,86 +1,153 @@
-use std::error::Error;
 use std::fs::File;
 use std::io;
 use std::io::{Read, Seek};
+use std::path::Path;
+
+use log::debug;
 use nom::Err::Incomplete;
-use nom::error::ErrorKind;
-use crate::mp3_frame::{frame_header, frame_sync, FrameHeader};
+
+use crate::mp3_frame::{frame_header, FrameHeader};
 
 const CHUNK_SIZE: usize = 1024;
 
-pub struct Mp3FrameParser {
-  file: File,
+/// Bytes skipped to find the next frame, like a tag or junk between frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resync {
+  pub offset: u64,
+  pub len: u64,
+}
+
+/// Iterates the frames of an MPEG audio stream, skipping anything in between
+pub struct Mp3FrameParser<R> {
+  reader: R,
   buffer: Vec<u8>,
-  ceiling: usize,
+  /// Stream offset of the start of `buffer`
+  offset: u64,
+  /// Start of the bytes skipped since the last frame
+  skipping: Option<u64>,
+  resyncs: Vec<Resync>,
+  done: bool,
 }
 
-impl Mp3FrameParser {
-  pub fn new(filename: &str) -> io::Result<Self> {
-    let file = File::open(filename)?;
-    Ok(Mp3FrameParser {
-      file,
+impl Mp3FrameParser<File> {
+  pub fn new(path: impl AsRef<Path>) -> io::Result<Self> {
+    Ok(Self::from_reader(File::open(path)?))
+  }
+}
+
+impl<R: Read + Seek> Mp3FrameParser<R> {
+  /// Offsets count from the start of the stream, instead of the current position
+  pub fn from_seekable(mut reader: R) -> io::Result<Self> {
+    let offset = reader.stream_position()?;
+    let mut parser = Self::from_reader(reader);
+    parser.offset = offset;
+    Ok(parser)
+  }
+}
+
+impl<R: Read> Mp3FrameParser<R> {
+  /// Parses frames from the current position of `reader`, like a file, stdin or an in-memory buffer
+  pub fn from_reader(reader: R) -> Self {
+    Mp3FrameParser {
+      reader,
       buffer: Vec::new(),
-      ceiling: 0,
-    })
+      offset: 0,
+      skipping: None,
+      resyncs: vec![],
+      done: false,
+    }
+  }
+
+  /// Where sync was lost so far
+  pub fn resyncs(&self) -> &[Resync] {
+    &self.resyncs
   }
 
-  fn seek_back(&mut self, delta: i64) {
-    self.ceiling -= delta as usize;
-    self.file.seek(io::SeekFrom::Current(-delta)).unwrap();
+  /// Stream offset of the next byte to parse
+  pub fn offset(&self) -> u64 {
+    self.offset
   }
 
-  fn read_more(&mut self) -> Result<(), Box<dyn Error>> {
-    let mut buffer: Vec<u8> = vec![0u8; CHUNK_SIZE];
-    let len = self.file.read(&mut buffer).expect("Cannot read file");
+  fn skip(&mut self, len: usize) {
     if len == 0 {
-      Err("EOF")?
-    } else {
-      self.buffer = buffer;
-      self.ceiling += len;
-      Ok(())
+      return;
     }
+    self.skipping.get_or_insert(self.offset);
+    self.buffer.drain(..len);
+    self.offset += len as u64;
   }
-}
 
-impl Iterator for Mp3FrameParser {
-  type Item = FrameHeader;
+  fn synced(&mut self) {
+    if let Some(start) = self.skipping.take() {
+      debug!("resync at {} after {} bytes", start, self.offset - start);
+      self.resyncs.push(Resync { offset: start, len: self.offset - start });
+    }
+  }
 
-  fn next(&mut self) -> Option<Self::Item> {
+  /// False at the end of the stream
+  fn read_more(&mut self) -> io::Result<bool> {
+    let mut chunk = [0u8; CHUNK_SIZE];
     loop {
-      let remaining = match frame_sync(&self.buffer) {
-        Ok((remaining, _)) => remaining,
-        Err(_) => {
-          match self.read_more() {
-            Ok(_) => continue,
-            Err(_) => return None, // EOF
-          }
+      match self.reader.read(&mut chunk) {
+        Ok(len) => {
+          self.buffer.extend_from_slice(&chunk[..len]);
+          return Ok(len > 0);
         }
-      };
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+        Err(e) => return Err(e),
+      }
+    }
+  }
 
-      self.buffer = remaining.to_vec();
+  fn next_frame(&mut self) -> io::Result<Option<FrameHeader>> {
+    loop {
+      let sync = self.buffer.windows(2).position(|word| word[0] == 0xff && word[1] & 0xe0 == 0xe0);
+      match sync {
+        Some(sync) => self.skip(sync),
+        None => {
+          // keep a last 0xff, it may start the next frame
+          let keep = usize::from(self.buffer.last() == Some(&0xff));
+          self.skip(self.buffer.len() - keep);
+          if !self.read_more()? {
+            return self.end();
+          }
+          continue;
+        }
+      }
       match frame_header(&self.buffer) {
-        Ok((remaining, frame)) => {
-          self.buffer = remaining.to_vec();
-          return Some(frame);
+        Ok((_, frame)) => {
+          self.synced();
+          self.buffer.drain(..frame.data.len());
+          self.offset += frame.data.len() as u64;
+          return Ok(Some(frame));
         }
         Err(Incomplete(_)) => {
-          let delta = self.buffer.len() as i64;
-          self.seek_back(delta);
-          match self.read_more() {
-            Ok(_) => continue,
-            Err(_) => return None, // EOF
+          if !self.read_more()? {
+            return self.end();
           }
         }
-        Err(nom::Err::Error(e)) if e.code == ErrorKind::Tag => {
-          println!("offset {} {:?}", self.ceiling - e.input.len(), e);
-          let (_, remainder) = e.input.split_at(1);
-          self.buffer = remainder.to_vec();
-        }
-        Err(e) => {
-          panic!("Parse error: {}", e);
-        }
+        Err(_) => self.skip(1),
       }
     }
   }
+
+  /// Anything left is skipped, like a truncated frame or an ID3v1 tag
+  fn end(&mut self) -> io::Result<Option<FrameHeader>> {
+    self.skip(self.buffer.len());
+    self.synced();
+    Ok(None)
+  }
 }
 
+impl<R: Read> Iterator for Mp3FrameParser<R> {
+  type Item = io::Result<FrameHeader>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+    let next = self.next_frame().transpose();
+    self.done = !matches!(next, Some(Ok(_)));
+    next
+  }
+}
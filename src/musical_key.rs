@@ -0,0 +1,177 @@
+use std::fmt;
+use std::str::FromStr;
+
+// https://mixedinkey.com/camelot-wheel/ and https://www.beatunes.com/en/open-key-notation.html
+
+/// Extended text descriptions other taggers (including Mixed In Key) use for the key.
+pub const EXTENDED_KEY_NAMES: [&str; 3] = ["initialkey", "initial key", "key"];
+
+const SHARPS: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+const MAJOR_NAMES: [&str; 12] = ["C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+const MINOR_NAMES: [&str; 12] = ["C", "C#", "D", "Eb", "E", "F", "F#", "G", "G#", "A", "Bb", "B"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MusicalKey {
+  /// Pitch class of the tonic, C = 0 through B = 11
+  Major(u8),
+  Minor(u8),
+  OffKey,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyNotation {
+  /// ID3v2.4 `TKEY` notation as written by rekordbox, e.g. `Am` or `F#`
+  Standard,
+  /// Mixed In Key, e.g. `8A`
+  Camelot,
+  /// Traktor, e.g. `1m`
+  OpenKey,
+}
+
+impl MusicalKey {
+  /// Parses standard, Camelot or Open Key notation, including combined Mixed In Key
+  /// values such as `8A/Am` or the `8A - 6` comment.
+  pub fn parse(text: &str) -> Option<MusicalKey> {
+    let text = text.trim();
+    Self::parse_single(text).or_else(|| {
+      text.split(['/', '-', ','])
+        .find_map(|part| Self::parse_single(part.trim()))
+    })
+  }
+
+  fn parse_single(text: &str) -> Option<MusicalKey> {
+    if text.is_empty() {
+      return None;
+    }
+    if text.eq_ignore_ascii_case("o") || text.eq_ignore_ascii_case("off") {
+      return Some(MusicalKey::OffKey);
+    }
+    Self::parse_wheel(text).or_else(|| Self::parse_standard(text))
+  }
+
+  fn parse_wheel(text: &str) -> Option<MusicalKey> {
+    let (index, _) = text.char_indices().last()?;
+    let (number, letter) = text.split_at(index);
+    let number = number.parse::<u8>().ok().filter(|n| (1..=12).contains(n))?;
+    match letter {
+      "A" | "a" => Some(Self::from_camelot(number, true)),
+      "B" | "b" => Some(Self::from_camelot(number, false)),
+      "m" => Some(Self::from_open_key(number, true)),
+      "d" => Some(Self::from_open_key(number, false)),
+      _ => None
+    }
+  }
+
+  fn parse_standard(text: &str) -> Option<MusicalKey> {
+    let mut chars = text.chars();
+    let mut pitch: i8 = match chars.next()?.to_ascii_uppercase() {
+      'C' => 0,
+      'D' => 2,
+      'E' => 4,
+      'F' => 5,
+      'G' => 7,
+      'A' => 9,
+      'B' => 11,
+      _ => return None
+    };
+    let mut rest = chars.as_str();
+    if let Some(stripped) = rest.strip_prefix(['#', '♯']) {
+      pitch += 1;
+      rest = stripped;
+    } else if let Some(stripped) = rest.strip_prefix(['b', '♭']) {
+      pitch -= 1;
+      rest = stripped;
+    }
+    let pitch = pitch.rem_euclid(12) as u8;
+    match rest.trim().to_ascii_lowercase().as_str() {
+      "" | "maj" | "major" => Some(MusicalKey::Major(pitch)),
+      "m" | "min" | "minor" => Some(MusicalKey::Minor(pitch)),
+      _ => None
+    }
+  }
+
+  fn from_camelot(number: u8, minor: bool) -> MusicalKey {
+    // 8B is C major, every step clockwise is a fifth up
+    Self::from_fifths((number + 4) % 12, minor)
+  }
+
+  fn from_open_key(number: u8, minor: bool) -> MusicalKey {
+    // 1d is C major
+    Self::from_fifths(number - 1, minor)
+  }
+
+  fn from_fifths(fifths: u8, minor: bool) -> MusicalKey {
+    let major = (fifths * 7) % 12;
+    if minor {
+      MusicalKey::Minor((major + 9) % 12)
+    } else {
+      MusicalKey::Major(major)
+    }
+  }
+
+  /// Position of the relative major on the circle of fifths, C = 0
+  fn fifths(&self) -> Option<u8> {
+    let major = match self {
+      MusicalKey::Major(pitch) => *pitch,
+      MusicalKey::Minor(pitch) => (pitch + 3) % 12,
+      MusicalKey::OffKey => return None,
+    };
+    Some((major * 7) % 12)
+  }
+
+  pub fn camelot(&self) -> Option<String> {
+    let number = (self.fifths()? + 7) % 12 + 1;
+    let letter = if self.is_minor() { 'A' } else { 'B' };
+    Some(format!("{}{}", number, letter))
+  }
+
+  pub fn open_key(&self) -> Option<String> {
+    let number = self.fifths()? + 1;
+    let letter = if self.is_minor() { 'm' } else { 'd' };
+    Some(format!("{}{}", number, letter))
+  }
+
+  pub fn standard(&self) -> String {
+    match self {
+      MusicalKey::Major(pitch) => MAJOR_NAMES[*pitch as usize % 12].to_string(),
+      MusicalKey::Minor(pitch) => format!("{}m", MINOR_NAMES[*pitch as usize % 12]),
+      MusicalKey::OffKey => "o".to_string(),
+    }
+  }
+
+  /// Always spells accidentals as sharps, e.g. `D#m` instead of `Ebm`
+  pub fn sharps(&self) -> String {
+    match self {
+      MusicalKey::Major(pitch) => SHARPS[*pitch as usize % 12].to_string(),
+      MusicalKey::Minor(pitch) => format!("{}m", SHARPS[*pitch as usize % 12]),
+      MusicalKey::OffKey => "o".to_string(),
+    }
+  }
+
+  /// Off-key has no place on the wheel and is always written as `o`
+  pub fn to_notation(&self, notation: KeyNotation) -> String {
+    match notation {
+      KeyNotation::Standard => Some(self.standard()),
+      KeyNotation::Camelot => self.camelot(),
+      KeyNotation::OpenKey => self.open_key(),
+    }.unwrap_or_else(|| "o".to_string())
+  }
+
+  pub fn is_minor(&self) -> bool {
+    matches!(self, MusicalKey::Minor(_))
+  }
+}
+
+impl fmt::Display for MusicalKey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.standard())
+  }
+}
+
+impl FromStr for MusicalKey {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    MusicalKey::parse(s).ok_or_else(|| format!("Invalid key: {}", s))
+  }
+}
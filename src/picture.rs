@@ -0,0 +1,150 @@
+// The picture types of an APIC frame and recognising image data by its first bytes
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Picture {
+  Other = 0x00,
+  /// 32x32 pixels, PNG only
+  Icon = 0x01,
+  OtherIcon = 0x02,
+  FrontCover = 0x03,
+  BackCover = 0x04,
+  Leaflet = 0x05,
+  Media = 0x06,
+  LeadArtist = 0x07,
+  Artist = 0x08,
+  Conductor = 0x09,
+  Band = 0x0a,
+  Composer = 0x0b,
+  Lyricist = 0x0c,
+  RecordingLocation = 0x0d,
+  DuringRecording = 0x0e,
+  DuringPerformance = 0x0f,
+  VideoCapture = 0x10,
+  BrightColouredFish = 0x11,
+  Illustration = 0x12,
+  BandLogo = 0x13,
+  PublisherLogo = 0x14,
+}
+
+pub static PICTURES: [Picture; 21] = [
+  Picture::Other, Picture::Icon, Picture::OtherIcon, Picture::FrontCover, Picture::BackCover, Picture::Leaflet,
+  Picture::Media, Picture::LeadArtist, Picture::Artist, Picture::Conductor, Picture::Band, Picture::Composer,
+  Picture::Lyricist, Picture::RecordingLocation, Picture::DuringRecording, Picture::DuringPerformance,
+  Picture::VideoCapture, Picture::BrightColouredFish, Picture::Illustration, Picture::BandLogo, Picture::PublisherLogo,
+];
+
+/// The picture type of an APIC frame, types above 0x14 are not defined
+impl TryFrom<u8> for Picture {
+  type Error = u8;
+
+  fn try_from(kind: u8) -> std::result::Result<Picture, u8> {
+    PICTURES.get(kind as usize).copied().ok_or(kind)
+  }
+}
+
+impl From<Picture> for u8 {
+  fn from(picture: Picture) -> u8 {
+    picture as u8
+  }
+}
+
+/// The MIME type of JPEG, PNG, GIF, WebP, BMP or TIFF image data
+pub fn mime_type(data: &[u8]) -> Option<&'static str> {
+  match data {
+    [0xff, 0xd8, 0xff, ..] => Some("image/jpeg"),
+    [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => Some("image/png"),
+    [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+    [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+    [b'B', b'M', ..] => Some("image/bmp"),
+    [b'I', b'I', 0x2a, 0x00, ..] | [b'M', b'M', 0x00, 0x2a, ..] => Some("image/tiff"),
+    _ => None
+  }
+}
+
+#[cfg(feature = "image")]
+pub use self::image_info::*;
+
+#[cfg(feature = "image")]
+mod image_info {
+  use std::io::Cursor;
+
+  use image::imageops::FilterType;
+  use image::{DynamicImage, ImageDecoder, ImageReader};
+
+  use crate::Result;
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+    WebP,
+  }
+
+  impl ImageFormat {
+    pub fn mime_type(&self) -> &'static str {
+      match self {
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Png => "image/png",
+        ImageFormat::Gif => "image/gif",
+        ImageFormat::WebP => "image/webp",
+      }
+    }
+
+    pub fn from_mime_type(mime_type: &str) -> Option<ImageFormat> {
+      match mime_type {
+        "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg),
+        "image/png" => Some(ImageFormat::Png),
+        "image/gif" => Some(ImageFormat::Gif),
+        "image/webp" => Some(ImageFormat::WebP),
+        _ => None
+      }
+    }
+
+    fn codec(&self) -> image::ImageFormat {
+      match self {
+        ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+        ImageFormat::Png => image::ImageFormat::Png,
+        ImageFormat::Gif => image::ImageFormat::Gif,
+        ImageFormat::WebP => image::ImageFormat::WebP,
+      }
+    }
+  }
+
+  #[derive(Debug, Clone, PartialEq, Eq)]
+  pub struct ImageInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub bits_per_pixel: u16,
+  }
+
+  /// Reads format, dimensions and colour depth from the image header, without decoding the pixels
+  pub fn image_info(data: &[u8]) -> Result<ImageInfo> {
+    let format = super::mime_type(data).and_then(ImageFormat::from_mime_type).ok_or("Unsupported image format")?;
+    let decoder = ImageReader::with_format(Cursor::new(data), format.codec()).into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    Ok(ImageInfo { format, width, height, bits_per_pixel: decoder.color_type().bits_per_pixel() })
+  }
+
+  /// Scales the image down to fit `max_dim` and encodes it as `format`, `quality` (1-100) only applies to JPEG,
+  /// WebP is always lossless.
+  pub fn normalize(data: &[u8], max_dim: u32, format: ImageFormat, quality: u8) -> Result<Vec<u8>> {
+    let mut image = image::load_from_memory(data)?;
+    if image.width() > max_dim || image.height() > max_dim {
+      image = image.resize(max_dim, max_dim, FilterType::Lanczos3);
+    }
+    let mut out = Cursor::new(vec![]);
+    match format {
+      ImageFormat::Jpeg => {
+        // JPEG has no alpha channel
+        let image = DynamicImage::ImageRgb8(image.into_rgb8());
+        image.write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality))?
+      }
+      ImageFormat::Png => image.write_to(&mut out, format.codec())?,
+      _ => DynamicImage::ImageRgba8(image.into_rgba8()).write_to(&mut out, format.codec())?,
+    }
+    Ok(out.into_inner())
+  }
+}
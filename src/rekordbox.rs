@@ -0,0 +1,456 @@
+use std::fs;
+use std::path::Path;
+
+use nom::bytes::complete::{tag, take};
+use nom::multi::{count, many0};
+use nom::number::complete::{be_u16, be_u32, be_u8};
+use nom::sequence::tuple;
+use nom::IResult;
+
+use crate::{Frame, Result, Tag};
+
+// Rekordbox writes its analysis to ANLZ files: a PMAI file header followed by tagged sections, each
+// with a big endian header length and total length. Sections that are not understood are kept as is.
+
+pub static ANLZ_MIME_TYPE: &str = "application/vnd.rekordbox.dat";
+pub static DAT_FILENAME: &str = "ANLZ0000.DAT";
+pub static EXT_FILENAME: &str = "ANLZ0000.EXT";
+
+pub static FILE_TAG: &str = "PMAI";
+pub static PATH_TAG: &str = "PPTH";
+pub static BEAT_GRID_TAG: &str = "PQTZ";
+pub static CUES_TAG: &str = "PCOB";
+pub static EXTENDED_CUES_TAG: &str = "PCO2";
+pub static PREVIEW_TAG: &str = "PWAV";
+pub static TINY_PREVIEW_TAG: &str = "PWV2";
+pub static DETAIL_TAG: &str = "PWV3";
+pub static COLOR_PREVIEW_TAG: &str = "PWV4";
+pub static COLOR_DETAIL_TAG: &str = "PWV5";
+
+const SECTION_HEADER: usize = 12;
+const CUE_ENTRY_HEADER: usize = 12;
+const CUE_ENTRY_LEN: usize = 56;
+/// The extended entry header also holds the hot cue number
+const EXTENDED_CUE_ENTRY_HEADER: usize = 16;
+const NO_LOOP: u32 = 0xffffffff;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+  pub tag: String,
+  /// Tag specific header fields following the tag and lengths
+  pub header: Vec<u8>,
+  pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anlz {
+  /// File header fields following the PMAI tag and lengths
+  pub header: Vec<u8>,
+  pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Beat {
+  /// Position within the bar, 1 to 4
+  pub beat_number: u16,
+  /// Beats per minute times 100
+  pub tempo: u16,
+  pub time_ms: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CueListKind {
+  Memory,
+  HotCue,
+  Unknown(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CueKind {
+  Cue,
+  Loop,
+  Unknown(u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+  /// 0 for memory cues, 1 for hot cue A and so on
+  pub hot_cue: u32,
+  pub kind: CueKind,
+  pub time_ms: u32,
+  pub loop_end_ms: Option<u32>,
+  /// Index in the rekordbox color table, only in extended cues
+  pub color_id: u8,
+  /// Only in extended cues
+  pub color: Option<(u8, u8, u8)>,
+  /// Only in extended cues
+  pub comment: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueList {
+  pub kind: CueListKind,
+  pub cues: Vec<Cue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaveformColumn {
+  /// 0 to 31
+  pub height: u8,
+  /// 0 to 7
+  pub whiteness: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorColumn {
+  /// 0 to 7
+  pub red: u8,
+  pub green: u8,
+  pub blue: u8,
+  /// 0 to 31
+  pub height: u8,
+}
+
+impl Beat {
+  pub fn bpm(&self) -> f32 {
+    self.tempo as f32 / 100.0
+  }
+}
+
+impl From<u32> for CueListKind {
+  fn from(kind: u32) -> CueListKind {
+    match kind {
+      0 => CueListKind::Memory,
+      1 => CueListKind::HotCue,
+      n => CueListKind::Unknown(n),
+    }
+  }
+}
+
+impl From<u8> for CueKind {
+  fn from(kind: u8) -> CueKind {
+    match kind {
+      1 => CueKind::Cue,
+      2 => CueKind::Loop,
+      n => CueKind::Unknown(n),
+    }
+  }
+}
+
+impl From<u8> for WaveformColumn {
+  fn from(byte: u8) -> WaveformColumn {
+    WaveformColumn { height: byte & 0x1f, whiteness: byte >> 5 }
+  }
+}
+
+impl Default for Anlz {
+  fn default() -> Anlz {
+    Anlz { header: vec![0, 0, 0, 1, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0], sections: vec![] }
+  }
+}
+
+impl Anlz {
+  /// The analysis file embedded in the tag under `filename`, usually [DAT_FILENAME] or [EXT_FILENAME]
+  pub fn read(tag: &Tag, filename: &str) -> Result<Option<Anlz>> {
+    match tag.object_by_filename(filename) {
+      Some(Frame::Object { mime_type, data, .. }) if mime_type == ANLZ_MIME_TYPE => Ok(Some(Anlz::parse(data)?)),
+      _ => Ok(None)
+    }
+  }
+
+  pub fn write(&self, tag: &mut Tag, filename: &str) {
+    tag.set_object(filename, ANLZ_MIME_TYPE, "", &self.to_bytes());
+  }
+
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Anlz> {
+    Anlz::parse(&fs::read(path)?)
+  }
+
+  pub fn parse(data: &[u8]) -> Result<Anlz> {
+    let (_, anlz) = anlz_file(data).map_err(|_| "Invalid ANLZ file")?;
+    Ok(anlz)
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let sections: Vec<u8> = self.sections.iter().flat_map(|s| s.to_bytes()).collect();
+    let len_header = SECTION_HEADER + self.header.len();
+    let mut out = Vec::with_capacity(len_header + sections.len());
+    out.extend_from_slice(FILE_TAG.as_bytes());
+    out.extend_from_slice(&(len_header as u32).to_be_bytes());
+    out.extend_from_slice(&((len_header + sections.len()) as u32).to_be_bytes());
+    out.extend_from_slice(&self.header);
+    out.extend_from_slice(&sections);
+    out
+  }
+
+  pub fn section(&self, tag: &str) -> Option<&Section> {
+    self.sections.iter().find(|s| s.tag == tag)
+  }
+
+  /// Path of the analysed track as rekordbox saw it
+  pub fn path(&self) -> Option<String> {
+    let data = &self.section(PATH_TAG)?.data;
+    let words: Vec<u16> = data.chunks_exact(2).map(|w| u16::from_be_bytes([w[0], w[1]])).collect();
+    Some(String::from_utf16_lossy(&words).trim_end_matches('\0').to_string())
+  }
+
+  pub fn beat_grid(&self) -> Result<Vec<Beat>> {
+    match self.section(BEAT_GRID_TAG) {
+      Some(section) => {
+        let (_, beats) = many0(beat)(&section.data).map_err(|_| "Invalid ANLZ beat grid")?;
+        Ok(beats)
+      }
+      None => Ok(vec![])
+    }
+  }
+
+  /// Both the memory and the hot cue lists, from extended cue sections when present
+  pub fn cue_lists(&self) -> Result<Vec<CueList>> {
+    let extended = self.sections.iter().any(|s| s.tag == EXTENDED_CUES_TAG);
+    self.sections.iter()
+      .filter(|s| s.tag == if extended { EXTENDED_CUES_TAG } else { CUES_TAG })
+      .map(|s| {
+        let parser = if extended { extended_cue_list } else { cue_list };
+        let (_, list) = parser(&s.header, &s.data).map_err(|_| "Invalid ANLZ cue list")?;
+        Ok(list)
+      })
+      .collect()
+  }
+
+  pub fn cues(&self) -> Result<Vec<Cue>> {
+    Ok(self.cue_lists()?.into_iter().flat_map(|list| list.cues).collect())
+  }
+
+  /// Replaces the cue lists in the place of the first existing one. Extended cue lists with the
+  /// comments and colors follow when there were any or a cue has a comment or color, because
+  /// rekordbox prefers them over the plain lists.
+  pub fn set_cue_lists(&mut self, lists: &[CueList]) {
+    let position = self.sections.iter().position(|s| s.tag == CUES_TAG || s.tag == EXTENDED_CUES_TAG)
+      .unwrap_or(self.sections.len());
+    let extended = self.sections.iter().any(|s| s.tag == EXTENDED_CUES_TAG)
+      || lists.iter().flat_map(|list| &list.cues).any(|cue| cue.color.is_some() || !cue.comment.is_empty());
+    self.sections.retain(|s| s.tag != CUES_TAG && s.tag != EXTENDED_CUES_TAG);
+    let position = position.min(self.sections.len());
+    let mut sections: Vec<Section> = lists.iter().map(CueList::to_section).collect();
+    if extended {
+      sections.extend(lists.iter().map(CueList::to_extended_section));
+    }
+    self.sections.splice(position..position, sections);
+  }
+
+  pub fn preview(&self) -> Option<Vec<WaveformColumn>> {
+    self.section(PREVIEW_TAG).map(|s| s.data.iter().map(|b| WaveformColumn::from(*b)).collect())
+  }
+
+  /// Heights from 0 to 15
+  pub fn tiny_preview(&self) -> Option<Vec<u8>> {
+    self.section(TINY_PREVIEW_TAG).map(|s| s.data.iter().map(|b| b & 0x0f).collect())
+  }
+
+  pub fn detail(&self) -> Option<Vec<WaveformColumn>> {
+    self.section(DETAIL_TAG).map(|s| s.data.iter().map(|b| WaveformColumn::from(*b)).collect())
+  }
+
+  /// Six bytes per column, the meaning of which is only partly known
+  pub fn color_preview(&self) -> Option<Vec<[u8; 6]>> {
+    self.section(COLOR_PREVIEW_TAG).map(|s| s.data.chunks_exact(6).map(|c| c.try_into().unwrap()).collect())
+  }
+
+  pub fn color_detail(&self) -> Option<Vec<ColorColumn>> {
+    self.section(COLOR_DETAIL_TAG).map(|s| s.data.chunks_exact(2).map(|c| {
+      let bits = u16::from_be_bytes([c[0], c[1]]);
+      ColorColumn {
+        red: (bits >> 13) as u8,
+        green: (bits >> 10 & 0x07) as u8,
+        blue: (bits >> 7 & 0x07) as u8,
+        height: (bits >> 2 & 0x1f) as u8,
+      }
+    }).collect())
+  }
+}
+
+impl CueList {
+  fn kind(&self) -> u32 {
+    match self.kind {
+      CueListKind::Memory => 0,
+      CueListKind::HotCue => 1,
+      CueListKind::Unknown(n) => n,
+    }
+  }
+
+  fn to_section(&self) -> Section {
+    let len = self.cues.len();
+    let mut header = self.kind().to_be_bytes().to_vec();
+    header.extend_from_slice(&[0, 0]);
+    header.extend_from_slice(&(len as u16).to_be_bytes());
+    header.extend_from_slice(&(len as u32).wrapping_sub(1).to_be_bytes());
+
+    let mut data = Vec::with_capacity(len * CUE_ENTRY_LEN);
+    for (index, cue) in self.cues.iter().enumerate() {
+      let previous = if index == 0 { 0xffff } else { index as u16 - 1 };
+      let next = if index + 1 == len { 0xffff } else { index as u16 + 1 };
+      data.extend_from_slice(b"PCPT");
+      data.extend_from_slice(&(CUE_ENTRY_HEADER as u32 + 16).to_be_bytes());
+      data.extend_from_slice(&(CUE_ENTRY_LEN as u32).to_be_bytes());
+      data.extend_from_slice(&cue.hot_cue.to_be_bytes());
+      data.extend_from_slice(&0u32.to_be_bytes());
+      data.extend_from_slice(&0x10000u32.to_be_bytes());
+      data.extend_from_slice(&previous.to_be_bytes());
+      data.extend_from_slice(&next.to_be_bytes());
+      data.extend_from_slice(&[cue.kind(), 0]);
+      data.extend_from_slice(&0x3e8u16.to_be_bytes());
+      data.extend_from_slice(&cue.time_ms.to_be_bytes());
+      data.extend_from_slice(&cue.loop_end_ms.unwrap_or(NO_LOOP).to_be_bytes());
+      data.extend_from_slice(&[0; 16]);
+    }
+    Section { tag: CUES_TAG.to_string(), header, data }
+  }
+
+  /// Extended entries have a UTF-16 comment and the color of the cue, which is left out when unknown
+  fn to_extended_section(&self) -> Section {
+    let mut header = self.kind().to_be_bytes().to_vec();
+    header.extend_from_slice(&(self.cues.len() as u16).to_be_bytes());
+    header.extend_from_slice(&[0, 0]);
+
+    let mut data = vec![];
+    for cue in &self.cues {
+      let mut entry = vec![];
+      entry.extend_from_slice(&cue.hot_cue.to_be_bytes());
+      entry.extend_from_slice(&[cue.kind(), 0, 0, 0]);
+      entry.extend_from_slice(&cue.time_ms.to_be_bytes());
+      entry.extend_from_slice(&cue.loop_end_ms.unwrap_or(NO_LOOP).to_be_bytes());
+      entry.push(cue.color_id);
+      entry.extend_from_slice(&[0; 7]);
+      entry.extend_from_slice(&[0; 4]); // loop numerator and denominator
+      let comment: Vec<u8> = if cue.comment.is_empty() {
+        vec![]
+      } else {
+        cue.comment.encode_utf16().chain([0]).flat_map(u16::to_be_bytes).collect()
+      };
+      entry.extend_from_slice(&(comment.len() as u32).to_be_bytes());
+      entry.extend_from_slice(&comment);
+      if let Some((red, green, blue)) = cue.color {
+        entry.extend_from_slice(&[0, red, green, blue]);
+      }
+      data.extend_from_slice(b"PCP2");
+      data.extend_from_slice(&(EXTENDED_CUE_ENTRY_HEADER as u32).to_be_bytes());
+      data.extend_from_slice(&((CUE_ENTRY_HEADER + entry.len()) as u32).to_be_bytes());
+      data.extend_from_slice(&entry);
+    }
+    Section { tag: EXTENDED_CUES_TAG.to_string(), header, data }
+  }
+}
+
+impl Cue {
+  fn kind(&self) -> u8 {
+    match self.kind {
+      CueKind::Cue => 1,
+      CueKind::Loop => 2,
+      CueKind::Unknown(n) => n,
+    }
+  }
+}
+
+impl Section {
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let len_header = SECTION_HEADER + self.header.len();
+    let mut out = Vec::with_capacity(len_header + self.data.len());
+    out.extend_from_slice(self.tag.as_bytes());
+    out.extend_from_slice(&(len_header as u32).to_be_bytes());
+    out.extend_from_slice(&((len_header + self.data.len()) as u32).to_be_bytes());
+    out.extend_from_slice(&self.header);
+    out.extend_from_slice(&self.data);
+    out
+  }
+}
+
+fn lengths(input: &[u8]) -> IResult<&[u8], (usize, usize)> {
+  let (input, (len_header, len_tag)) = tuple((be_u32, be_u32))(input)?;
+  if len_header < SECTION_HEADER as u32 || len_tag < len_header {
+    return Err(nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Verify)));
+  }
+  Ok((input, (len_header as usize - SECTION_HEADER, (len_tag - len_header) as usize)))
+}
+
+fn section(input: &[u8]) -> IResult<&[u8], Section> {
+  let (input, tag) = take(4usize)(input)?;
+  let (input, (len_header, len_data)) = lengths(input)?;
+  let (input, (header, data)) = tuple((take(len_header), take(len_data)))(input)?;
+  let tag = String::from_utf8_lossy(tag).to_string();
+  Ok((input, Section { tag, header: header.to_vec(), data: data.to_vec() }))
+}
+
+fn anlz_file(input: &[u8]) -> IResult<&[u8], Anlz> {
+  let (input, _) = tag(FILE_TAG)(input)?;
+  let (input, (len_header, len_data)) = lengths(input)?;
+  let (input, (header, data)) = tuple((take(len_header), take(len_data)))(input)?;
+  let (rest, sections) = many0(section)(data)?;
+  if !rest.is_empty() {
+    return Err(nom::Err::Error(nom::error::Error::new(rest, nom::error::ErrorKind::Eof)));
+  }
+  Ok((input, Anlz { header: header.to_vec(), sections }))
+}
+
+fn beat(input: &[u8]) -> IResult<&[u8], Beat> {
+  let (input, (beat_number, tempo, time_ms)) = tuple((be_u16, be_u16, be_u32))(input)?;
+  Ok((input, Beat { beat_number, tempo, time_ms }))
+}
+
+/// Cue list header: type, two unknown bytes, cue count and memory count
+fn cue_list<'a>(header: &'a [u8], data: &'a [u8]) -> IResult<&'a [u8], CueList> {
+  let (_, (kind, _, len)) = tuple((be_u32, be_u16, be_u16))(header)?;
+  let (input, cues) = count(cue_entry, len as usize)(data)?;
+  Ok((input, CueList { kind: CueListKind::from(kind), cues }))
+}
+
+/// Extended cue list header: type, cue count and two unknown bytes
+fn extended_cue_list<'a>(header: &'a [u8], data: &'a [u8]) -> IResult<&'a [u8], CueList> {
+  let (_, (kind, len)) = tuple((be_u32, be_u16))(header)?;
+  let (input, cues) = count(extended_cue_entry, len as usize)(data)?;
+  Ok((input, CueList { kind: CueListKind::from(kind), cues }))
+}
+
+fn entry<'a>(input: &'a [u8], magic: &'static str) -> IResult<&'a [u8], &'a [u8]> {
+  let (input, (_, _, len_entry)) = tuple((tag(magic), be_u32, be_u32))(input)?;
+  take((len_entry as usize).saturating_sub(CUE_ENTRY_HEADER))(input)
+}
+
+fn loop_end(loop_time: u32) -> Option<u32> {
+  if loop_time == NO_LOOP { None } else { Some(loop_time) }
+}
+
+fn cue_entry(input: &[u8]) -> IResult<&[u8], Cue> {
+  let (input, entry) = entry(input, "PCPT")?;
+  let (_, (hot_cue, _status, _, _order_first, _order_last, kind, _, _, time_ms, loop_time)) =
+    tuple((be_u32, be_u32, be_u32, be_u16, be_u16, be_u8, be_u8, be_u16, be_u32, be_u32))(entry)?;
+  let cue = Cue {
+    hot_cue,
+    kind: CueKind::from(kind),
+    time_ms,
+    loop_end_ms: loop_end(loop_time),
+    color_id: 0,
+    color: None,
+    comment: String::new(),
+  };
+  Ok((input, cue))
+}
+
+fn extended_cue_entry(input: &[u8]) -> IResult<&[u8], Cue> {
+  let (input, entry) = entry(input, "PCP2")?;
+  let (rest, (hot_cue, kind, _, time_ms, loop_time, color_id, _, _, _)) =
+    tuple((be_u32, be_u8, take(3usize), be_u32, be_u32, be_u8, take(7usize), be_u16, be_u16))(entry)?;
+  let (comment, color) = match be_u32::<_, nom::error::Error<_>>(rest) {
+    Ok((rest, len_comment)) => {
+      let (rest, words) = count(be_u16, len_comment as usize / 2)(rest)?;
+      let comment = String::from_utf16_lossy(&words).trim_end_matches('\0').to_string();
+      let color = match tuple((be_u8::<_, nom::error::Error<_>>, be_u8, be_u8, be_u8))(rest) {
+        Ok((_, (_code, red, green, blue))) => Some((red, green, blue)),
+        Err(_) => None
+      };
+      (comment, color)
+    }
+    Err(_) => (String::new(), None)
+  };
+  let cue = Cue { hot_cue, kind: CueKind::from(kind), time_ms, loop_end_ms: loop_end(loop_time), color_id, color, comment };
+  Ok((input, cue))
+}
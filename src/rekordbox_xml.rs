@@ -0,0 +1,364 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+use crate::cue_points::{BeatGrid, CuePoint, Cues, GridMarker, Loop, Mp3Timing, Vendor};
+use crate::musical_key::{KeyNotation, MusicalKey};
+use crate::{ID3rs, Result, Tag};
+
+// The DJ_PLAYLISTS collection rekordbox imports and exports. Positions are in seconds on the
+// rekordbox timeline, ratings range from 0 to 255 in steps of 51.
+
+pub static RATING_AUTHOR: &str = "rekordbox";
+
+const LOCATION_PREFIX: &str = "file://localhost";
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+const LOCATION_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'/').remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+const LOOP_TYPE: u8 = 4;
+const RATING_STEP: u8 = 51;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "DJ_PLAYLISTS")]
+pub struct DjPlaylists {
+  #[serde(rename = "@Version")]
+  pub version: String,
+  #[serde(rename = "PRODUCT")]
+  pub product: Product,
+  #[serde(rename = "COLLECTION")]
+  pub collection: Collection,
+  #[serde(rename = "PLAYLISTS", skip_serializing_if = "Option::is_none")]
+  pub playlists: Option<Playlists>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+  #[serde(rename = "@Name")]
+  pub name: String,
+  #[serde(rename = "@Version")]
+  pub version: String,
+  #[serde(rename = "@Company")]
+  pub company: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Collection {
+  #[serde(rename = "@Entries")]
+  pub entries: usize,
+  #[serde(rename = "TRACK", default)]
+  pub tracks: Vec<Track>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Playlists {
+  #[serde(rename = "NODE", default)]
+  pub nodes: Vec<Node>,
+}
+
+/// A folder (type 0) or playlist (type 1) referring to collection tracks by id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+  #[serde(rename = "@Type")]
+  pub kind: u8,
+  #[serde(rename = "@Name")]
+  pub name: String,
+  #[serde(rename = "@Count", skip_serializing_if = "Option::is_none")]
+  pub count: Option<usize>,
+  #[serde(rename = "@KeyType", skip_serializing_if = "Option::is_none")]
+  pub key_type: Option<u8>,
+  #[serde(rename = "@Entries", skip_serializing_if = "Option::is_none")]
+  pub entries: Option<usize>,
+  #[serde(rename = "NODE", default)]
+  pub nodes: Vec<Node>,
+  #[serde(rename = "TRACK", default)]
+  pub tracks: Vec<PlaylistTrack>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaylistTrack {
+  #[serde(rename = "@Key")]
+  pub key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Track {
+  #[serde(rename = "@TrackID")]
+  pub track_id: u32,
+  #[serde(rename = "@Name", skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  #[serde(rename = "@Artist", skip_serializing_if = "Option::is_none")]
+  pub artist: Option<String>,
+  #[serde(rename = "@Album", skip_serializing_if = "Option::is_none")]
+  pub album: Option<String>,
+  #[serde(rename = "@Genre", skip_serializing_if = "Option::is_none")]
+  pub genre: Option<String>,
+  #[serde(rename = "@Kind", skip_serializing_if = "Option::is_none")]
+  pub kind: Option<String>,
+  #[serde(rename = "@Size", skip_serializing_if = "Option::is_none")]
+  pub size: Option<u64>,
+  #[serde(rename = "@TotalTime", skip_serializing_if = "Option::is_none")]
+  pub total_time: Option<u32>,
+  #[serde(rename = "@AverageBpm", skip_serializing_if = "Option::is_none")]
+  pub average_bpm: Option<f64>,
+  #[serde(rename = "@Comments", skip_serializing_if = "Option::is_none")]
+  pub comments: Option<String>,
+  #[serde(rename = "@Rating", skip_serializing_if = "Option::is_none")]
+  pub rating: Option<u8>,
+  #[serde(rename = "@Location")]
+  pub location: String,
+  #[serde(rename = "@Tonality", skip_serializing_if = "Option::is_none")]
+  pub tonality: Option<String>,
+  #[serde(rename = "TEMPO", default)]
+  pub tempos: Vec<Tempo>,
+  #[serde(rename = "POSITION_MARK", default)]
+  pub position_marks: Vec<PositionMark>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tempo {
+  /// Seconds
+  #[serde(rename = "@Inizio")]
+  pub start: f64,
+  #[serde(rename = "@Bpm")]
+  pub bpm: f64,
+  #[serde(rename = "@Metro")]
+  pub metro: String,
+  /// Beat within the bar
+  #[serde(rename = "@Battito")]
+  pub beat: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionMark {
+  #[serde(rename = "@Name")]
+  pub name: String,
+  /// 0 cue, 1 fade-in, 2 fade-out, 3 load, 4 loop
+  #[serde(rename = "@Type")]
+  pub kind: u8,
+  /// Seconds
+  #[serde(rename = "@Start")]
+  pub start: f64,
+  #[serde(rename = "@End", skip_serializing_if = "Option::is_none")]
+  pub end: Option<f64>,
+  /// Hot cue slot, -1 for memory cues
+  #[serde(rename = "@Num")]
+  pub num: i32,
+  #[serde(rename = "@Red", skip_serializing_if = "Option::is_none")]
+  pub red: Option<u8>,
+  #[serde(rename = "@Green", skip_serializing_if = "Option::is_none")]
+  pub green: Option<u8>,
+  #[serde(rename = "@Blue", skip_serializing_if = "Option::is_none")]
+  pub blue: Option<u8>,
+}
+
+impl Default for DjPlaylists {
+  fn default() -> DjPlaylists {
+    DjPlaylists {
+      version: "1.0.0".to_string(),
+      product: Product { name: "id3rs".to_string(), version: env!("CARGO_PKG_VERSION").to_string(), company: "".to_string() },
+      collection: Collection { entries: 0, tracks: vec![] },
+      playlists: None,
+    }
+  }
+}
+
+impl DjPlaylists {
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<DjPlaylists> {
+    DjPlaylists::parse(&fs::read_to_string(path)?)
+  }
+
+  pub fn parse(xml: &str) -> Result<DjPlaylists> {
+    Ok(quick_xml::de::from_str(xml)?)
+  }
+
+  pub fn to_xml(&self) -> Result<String> {
+    let mut xml = XML_DECLARATION.to_string();
+    let mut serializer = quick_xml::se::Serializer::new(&mut xml);
+    serializer.indent(' ', 2);
+    self.serialize(serializer)?;
+    xml.push('\n');
+    Ok(xml)
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    Ok(fs::write(path, self.to_xml()?)?)
+  }
+
+  /// Collection of the MP3 files in `dir` and its subdirectories, files without a readable tag or cues are skipped
+  pub fn from_directory<P: AsRef<Path>>(dir: P) -> Result<DjPlaylists> {
+    let mut files = vec![];
+    mp3_files(dir.as_ref(), &mut files)?;
+    files.sort();
+
+    let mut playlists = DjPlaylists::default();
+    for path in files {
+      let id = playlists.collection.tracks.len() as u32 + 1;
+      match ID3rs::read(&path).and_then(|tag| Track::from_tag(id, &tag)) {
+        Ok(track) => playlists.collection.tracks.push(track),
+        Err(e) => debug!("skipping {:?}: {}", path, e)
+      }
+    }
+    playlists.collection.entries = playlists.collection.tracks.len();
+    Ok(playlists)
+  }
+
+  /// The collection entry whose `Location` refers to `path`
+  pub fn track<P: AsRef<Path>>(&self, path: P) -> Option<&Track> {
+    let path = canonical(path.as_ref());
+    self.collection.tracks.iter().find(|track| track.path().map(|p| canonical(&p)) == Some(path.clone()))
+  }
+
+  /// Applies the matching collection entry to the tag, returns whether there was one
+  pub fn import(&self, tag: &mut ID3rs, vendors: &[Vendor]) -> Result<bool> {
+    match self.track(&tag.path) {
+      Some(track) => {
+        track.apply(tag, vendors)?;
+        Ok(true)
+      }
+      None => Ok(false)
+    }
+  }
+
+  /// Applies every entry of which the file exists and writes its tag, returns the files updated
+  pub fn import_all(&self, vendors: &[Vendor]) -> Result<Vec<PathBuf>> {
+    let mut updated = vec![];
+    for track in &self.collection.tracks {
+      let path = match track.path() {
+        Some(path) if path.exists() => path,
+        _ => continue
+      };
+      let mut tag = ID3rs::read(&path)?;
+      track.apply(&mut tag, vendors)?;
+      tag.write()?;
+      updated.push(path);
+    }
+    Ok(updated)
+  }
+}
+
+impl Track {
+  pub fn from_tag(track_id: u32, tag: &ID3rs) -> Result<Track> {
+    let path = canonical(&tag.path);
+    let offset_ms = Vendor::Rekordbox.offset_ms(&Mp3Timing::read(tag)?);
+    let cues = tag.cues()?;
+    let seconds = |ms: f64| (ms + offset_ms).round() / 1000.0;
+    let color = |color: Option<(u8, u8, u8)>| (color.map(|c| c.0), color.map(|c| c.1), color.map(|c| c.2));
+
+    let mut position_marks: Vec<PositionMark> = cues.cue_points.iter().map(|cue| {
+      let (red, green, blue) = color(cue.color);
+      PositionMark { name: cue.name.clone(), kind: 0, start: seconds(cue.position_ms), end: None, num: num(cue.index), red, green, blue }
+    }).collect();
+    position_marks.extend(cues.loops.iter().map(|lp| {
+      let (red, green, blue) = color(lp.color);
+      PositionMark { name: lp.name.clone(), kind: LOOP_TYPE, start: seconds(lp.start_ms), end: Some(seconds(lp.end_ms)), num: num(lp.index), red, green, blue }
+    }));
+    let tempos = cues.beat_grid.map(|grid| grid.markers.iter()
+      .map(|marker| Tempo { start: seconds(marker.position_ms), bpm: marker.bpm, metro: "4/4".to_string(), beat: 1 })
+      .collect()).unwrap_or_default();
+
+    Ok(Track {
+      track_id,
+      name: tag.title().map(str::to_string),
+      artist: tag.artist().map(str::to_string),
+      album: tag.album().map(str::to_string),
+      genre: tag.genre().map(str::to_string),
+      kind: Some("MP3 File".to_string()),
+      size: fs::metadata(&path).ok().map(|m| m.len()),
+      total_time: None,
+      average_bpm: tag.bpm(),
+      comments: tag.comment().map(str::to_string),
+      rating: tag.popularities().iter().map(|(_, rating)| *rating).max().map(|stars| stars * RATING_STEP),
+      location: location(&path),
+      tonality: tag.musical_key().map(|key| key.standard()),
+      tempos,
+      position_marks,
+    })
+  }
+
+  pub fn path(&self) -> Option<PathBuf> {
+    let path = self.location.strip_prefix(LOCATION_PREFIX)?;
+    Some(PathBuf::from(percent_decode_str(path).decode_utf8().ok()?.to_string()))
+  }
+
+  pub fn musical_key(&self) -> Option<MusicalKey> {
+    self.tonality.as_deref().and_then(MusicalKey::parse)
+  }
+
+  /// Position marks and tempos as neutral cues, `timing` of the file they belong to
+  pub fn cues(&self, timing: &Mp3Timing) -> Cues {
+    let offset_ms = Vendor::Rekordbox.offset_ms(timing);
+    let ms = |seconds: f64| seconds * 1000.0 - offset_ms;
+    let mut cues = Cues::default();
+    for mark in &self.position_marks {
+      let index = u8::try_from(mark.num).ok();
+      let color = match (mark.red, mark.green, mark.blue) {
+        (Some(red), Some(green), Some(blue)) => Some((red, green, blue)),
+        _ => None
+      };
+      match (mark.kind, mark.end) {
+        (LOOP_TYPE, Some(end)) => cues.loops.push(Loop { index, start_ms: ms(mark.start), end_ms: ms(end), name: mark.name.clone(), color }),
+        _ => cues.cue_points.push(CuePoint { index, position_ms: ms(mark.start), name: mark.name.clone(), color }),
+      }
+    }
+    if !self.tempos.is_empty() {
+      let markers = self.tempos.iter().map(|tempo| GridMarker { position_ms: ms(tempo.start), bpm: tempo.bpm }).collect();
+      cues.beat_grid = Some(BeatGrid { markers });
+    }
+    cues
+  }
+
+  /// Sets the text frames, rating, BPM and key, and writes the cues to `vendors`
+  pub fn apply(&self, tag: &mut ID3rs, vendors: &[Vendor]) -> Result<()> {
+    let texts = [
+      (&self.name, Tag::set_title as fn(&mut Tag, &str)),
+      (&self.artist, Tag::set_artist),
+      (&self.album, Tag::set_album),
+      (&self.genre, Tag::set_genre),
+    ];
+    for (value, setter) in texts {
+      if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+        setter(tag, value);
+      }
+    }
+    if let Some(rating) = self.rating {
+      tag.set_popularity(RATING_AUTHOR, ((rating as u16 + RATING_STEP as u16 / 2) / RATING_STEP as u16).min(5) as u8);
+    }
+    if let Some(bpm) = self.average_bpm.filter(|bpm| *bpm > 0.0) {
+      tag.set_bpm(bpm);
+    }
+    if let Some(key) = self.musical_key() {
+      tag.set_musical_key(key, KeyNotation::Standard);
+    }
+    let cues = self.cues(&Mp3Timing::read(tag)?);
+    if !cues.is_empty() {
+      tag.set_cues(&cues, vendors)?;
+    }
+    Ok(())
+  }
+}
+
+fn num(index: Option<u8>) -> i32 {
+  index.map(|i| i as i32).unwrap_or(-1)
+}
+
+fn location(path: &Path) -> String {
+  format!("{}{}", LOCATION_PREFIX, utf8_percent_encode(&path.to_string_lossy(), LOCATION_SET))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+  path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn mp3_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.is_dir() {
+      mp3_files(&path, files)?;
+    } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("mp3")) {
+      files.push(path);
+    }
+  }
+  Ok(())
+}
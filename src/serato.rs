@@ -0,0 +1,454 @@
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use nom::bytes::complete::{tag, take, take_until};
+use nom::multi::count;
+use nom::number::complete::{be_f32, be_u32, be_u8};
+use nom::sequence::{terminated, tuple};
+use nom::IResult;
+
+use crate::{Frame, Result, Tag};
+
+// https://github.com/Holzhaus/serato-tags/blob/main/docs/serato_markers2.md and friends
+
+pub static MARKERS2_OBJECT: &str = "Serato Markers2";
+pub static BEATGRID_OBJECT: &str = "Serato BeatGrid";
+pub static AUTOTAGS_OBJECT: &str = "Serato Autotags";
+pub static OVERVIEW_OBJECT: &str = "Serato Overview";
+pub static ANALYSIS_OBJECT: &str = "Serato Analysis";
+
+const MIME_TYPE: &str = "application/octet-stream";
+const LINE_LENGTH: usize = 72;
+const MARKERS2_MIN_LEN: usize = 470;
+const OVERVIEW_BLOCK: usize = 16;
+
+/// Serato leaves out the `=` padding and decodes leniently
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+  &alphabet::STANDARD,
+  GeneralPurposeConfig::new()
+    .with_encode_padding(false)
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+    .with_decode_allow_trailing_bits(true));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+  pub index: u8,
+  pub position_ms: u32,
+  pub color: Rgb,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+  pub index: u8,
+  pub start_ms: u32,
+  pub end_ms: u32,
+  pub color: Rgb,
+  pub locked: bool,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Markers2Entry {
+  Color(Rgb),
+  Cue(Cue),
+  Loop(Loop),
+  BpmLock(bool),
+  Unknown { name: String, data: Vec<u8> },
+}
+
+/// How the base64 payload was laid out, so unchanged markers serialise to the same bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Markers2Layout {
+  /// Zero bytes after the last entry, including the terminator
+  pub trailing_zeros: usize,
+  pub trailing_newline: bool,
+  pub padded: bool,
+  pub dangling: Option<u8>,
+  /// Data is padded with zeros up to this length
+  pub min_len: usize,
+}
+
+impl Default for Markers2Layout {
+  fn default() -> Self {
+    Markers2Layout { trailing_zeros: 1, trailing_newline: false, padded: false, dangling: None, min_len: MARKERS2_MIN_LEN }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Markers2 {
+  pub entries: Vec<Markers2Entry>,
+  pub layout: Markers2Layout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeatGridMarker {
+  NonTerminal { position: f32, beats_till_next: u32 },
+  Terminal { position: f32, bpm: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BeatGrid {
+  pub markers: Vec<BeatGridMarker>,
+  pub footer: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Autotags {
+  pub bpm: f64,
+  pub auto_gain: f64,
+  pub gain_db: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Overview {
+  pub blocks: Vec<[u8; OVERVIEW_BLOCK]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Analysis {
+  pub major: u8,
+  pub minor: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Serato {
+  pub markers: Option<Markers2>,
+  pub beatgrid: Option<BeatGrid>,
+  pub autotags: Option<Autotags>,
+  pub overview: Option<Overview>,
+  pub analysis: Option<Analysis>,
+}
+
+impl Serato {
+  pub fn read(tag: &Tag) -> Result<Serato> {
+    Ok(Serato {
+      markers: object_data(tag, MARKERS2_OBJECT).map(Markers2::parse).transpose()?,
+      beatgrid: object_data(tag, BEATGRID_OBJECT).map(BeatGrid::parse).transpose()?,
+      autotags: object_data(tag, AUTOTAGS_OBJECT).map(Autotags::parse).transpose()?,
+      overview: object_data(tag, OVERVIEW_OBJECT).map(Overview::parse).transpose()?,
+      analysis: object_data(tag, ANALYSIS_OBJECT).map(Analysis::parse).transpose()?,
+    })
+  }
+
+  pub fn write(&self, tag: &mut Tag) {
+    if let Some(markers) = &self.markers {
+      set_object(tag, MARKERS2_OBJECT, &markers.to_bytes());
+    }
+    if let Some(beatgrid) = &self.beatgrid {
+      set_object(tag, BEATGRID_OBJECT, &beatgrid.to_bytes());
+    }
+    if let Some(autotags) = &self.autotags {
+      set_object(tag, AUTOTAGS_OBJECT, &autotags.to_bytes());
+    }
+    if let Some(overview) = &self.overview {
+      set_object(tag, OVERVIEW_OBJECT, &overview.to_bytes());
+    }
+    if let Some(analysis) = &self.analysis {
+      set_object(tag, ANALYSIS_OBJECT, &analysis.to_bytes());
+    }
+  }
+}
+
+fn object_data<'a>(tag: &'a Tag, description: &str) -> Option<&'a [u8]> {
+  tag.object_by_description(description).and_then(|f| match f {
+    Frame::Object { data, .. } => Some(data.as_slice()),
+    _ => None
+  })
+}
+
+/// Keeps the mime type of an existing object, other tools write `application/json`
+fn set_object(tag: &mut Tag, description: &str, data: &[u8]) {
+  let mime_type = match tag.object_by_description(description) {
+    Some(Frame::Object { mime_type, .. }) => mime_type.clone(),
+    _ => MIME_TYPE.to_string()
+  };
+  tag.set_object_by_description(description, &mime_type, data);
+}
+
+impl Markers2 {
+  pub fn parse(data: &[u8]) -> Result<Markers2> {
+    let body = data.strip_prefix(b"\x01\x01").ok_or("Invalid Serato Markers2 version")?;
+    let end = body.iter().position(|b| *b == 0).unwrap_or(body.len());
+    let (text, padding) = body.split_at(end);
+
+    let mut chars: Vec<u8> = text.iter().copied().filter(|b| *b != b'\n').collect();
+    let padded = chars.ends_with(b"=");
+    while chars.ends_with(b"=") {
+      chars.pop();
+    }
+    let dangling = if chars.len() % 4 == 1 { chars.pop() } else { None };
+    let payload = BASE64.decode(&chars)?;
+
+    let (rest, entries) = markers2_entries(&payload).map_err(|_| "Invalid Serato Markers2 entries")?;
+    if rest.iter().any(|b| *b != 0) {
+      Err("Invalid Serato Markers2 terminator")?
+    }
+
+    let layout = Markers2Layout {
+      trailing_zeros: rest.len(),
+      trailing_newline: text.ends_with(b"\n"),
+      padded,
+      dangling,
+      min_len: if padding.is_empty() { 0 } else { data.len() },
+    };
+    Ok(Markers2 { entries, layout })
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut payload = vec![1u8, 1];
+    for entry in &self.entries {
+      let data = entry.to_bytes();
+      payload.extend_from_slice(entry.name().as_bytes());
+      payload.push(0);
+      payload.extend_from_slice(&(data.len() as u32).to_be_bytes());
+      payload.extend_from_slice(&data);
+    }
+    payload.resize(payload.len() + self.layout.trailing_zeros, 0);
+
+    let mut text = BASE64.encode(payload).into_bytes();
+    text.extend(self.layout.dangling);
+    if self.layout.padded {
+      text.resize(text.len().div_ceil(4) * 4, b'=');
+    }
+
+    let mut data = vec![1u8, 1];
+    for (index, line) in text.chunks(LINE_LENGTH).enumerate() {
+      if index > 0 {
+        data.push(b'\n');
+      }
+      data.extend_from_slice(line);
+    }
+    if self.layout.trailing_newline {
+      data.push(b'\n');
+    }
+    if data.len() < self.layout.min_len {
+      data.resize(self.layout.min_len, 0);
+    }
+    data
+  }
+
+  pub fn color(&self) -> Option<Rgb> {
+    self.entries.iter().find_map(|e| match e {
+      Markers2Entry::Color(color) => Some(*color),
+      _ => None
+    })
+  }
+
+  pub fn cues(&self) -> Vec<&Cue> {
+    self.entries.iter().filter_map(|e| match e {
+      Markers2Entry::Cue(cue) => Some(cue),
+      _ => None
+    }).collect()
+  }
+
+  pub fn loops(&self) -> Vec<&Loop> {
+    self.entries.iter().filter_map(|e| match e {
+      Markers2Entry::Loop(lp) => Some(lp),
+      _ => None
+    }).collect()
+  }
+
+  /// Replaces the cues and loops, keeping the other entries in place
+  pub fn set_cues(&mut self, cues: Vec<Cue>, loops: Vec<Loop>) {
+    self.entries.retain(|e| !matches!(e, Markers2Entry::Cue(_) | Markers2Entry::Loop(_)));
+    self.entries.extend(cues.into_iter().map(Markers2Entry::Cue));
+    self.entries.extend(loops.into_iter().map(Markers2Entry::Loop));
+  }
+}
+
+impl Markers2Entry {
+  pub fn name(&self) -> &str {
+    match self {
+      Markers2Entry::Color(_) => "COLOR",
+      Markers2Entry::Cue(_) => "CUE",
+      Markers2Entry::Loop(_) => "LOOP",
+      Markers2Entry::BpmLock(_) => "BPMLOCK",
+      Markers2Entry::Unknown { name, .. } => name,
+    }
+  }
+
+  fn parse(name: &str, data: &[u8]) -> Markers2Entry {
+    let parsed = match name {
+      "COLOR" => complete(color_entry(data)).map(Markers2Entry::Color),
+      "CUE" => complete(cue_entry(data)).map(Markers2Entry::Cue),
+      "LOOP" => complete(loop_entry(data)).map(Markers2Entry::Loop),
+      "BPMLOCK" if data.len() == 1 => Some(Markers2Entry::BpmLock(data[0] != 0)),
+      _ => None
+    };
+    parsed.unwrap_or_else(|| Markers2Entry::Unknown { name: name.to_string(), data: data.to_vec() })
+  }
+
+  fn to_bytes(&self) -> Vec<u8> {
+    let mut out = vec![];
+    match self {
+      Markers2Entry::Color(color) => {
+        out.push(0);
+        out.extend_from_slice(&[color.0, color.1, color.2]);
+      }
+      Markers2Entry::Cue(cue) => {
+        out.extend_from_slice(&[0, cue.index]);
+        out.extend_from_slice(&cue.position_ms.to_be_bytes());
+        out.extend_from_slice(&[0, cue.color.0, cue.color.1, cue.color.2, 0, 0]);
+        out.extend_from_slice(cue.name.as_bytes());
+        out.push(0);
+      }
+      Markers2Entry::Loop(lp) => {
+        out.extend_from_slice(&[0, lp.index]);
+        out.extend_from_slice(&lp.start_ms.to_be_bytes());
+        out.extend_from_slice(&lp.end_ms.to_be_bytes());
+        out.extend_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        out.extend_from_slice(&[0, lp.color.0, lp.color.1, lp.color.2, lp.locked as u8]);
+        out.extend_from_slice(lp.name.as_bytes());
+        out.push(0);
+      }
+      Markers2Entry::BpmLock(locked) => out.push(*locked as u8),
+      Markers2Entry::Unknown { data, .. } => out.extend_from_slice(data),
+    }
+    out
+  }
+}
+
+/// Only accepts entries that serialise back to the same bytes
+fn complete<T>(result: IResult<&[u8], T>) -> Option<T> {
+  match result {
+    Ok((&[], value)) => Some(value),
+    _ => None
+  }
+}
+
+fn c_string(input: &[u8]) -> IResult<&[u8], String> {
+  let (input, bytes) = terminated(take_until("\x00"), tag("\x00"))(input)?;
+  Ok((input, String::from_utf8_lossy(bytes).to_string()))
+}
+
+fn rgb(input: &[u8]) -> IResult<&[u8], Rgb> {
+  let (input, (r, g, b)) = tuple((be_u8, be_u8, be_u8))(input)?;
+  Ok((input, Rgb(r, g, b)))
+}
+
+fn markers2_entries(input: &[u8]) -> IResult<&[u8], Vec<Markers2Entry>> {
+  let (mut input, _) = tag(b"\x01\x01")(input)?;
+  let mut entries = vec![];
+  while input.first().is_some_and(|b| *b != 0) {
+    let (rest, (name, len)) = tuple((c_string, be_u32))(input)?;
+    let (rest, data) = take(len)(rest)?;
+    entries.push(Markers2Entry::parse(&name, data));
+    input = rest;
+  }
+  Ok((input, entries))
+}
+
+fn color_entry(input: &[u8]) -> IResult<&[u8], Rgb> {
+  let (input, (_, color)) = tuple((tag(b"\x00"), rgb))(input)?;
+  Ok((input, color))
+}
+
+fn cue_entry(input: &[u8]) -> IResult<&[u8], Cue> {
+  let (input, (_, index, position_ms, _, color, _, name)) =
+    tuple((tag(b"\x00"), be_u8, be_u32, tag(b"\x00"), rgb, tag(b"\x00\x00"), c_string))(input)?;
+  Ok((input, Cue { index, position_ms, color, name }))
+}
+
+fn loop_entry(input: &[u8]) -> IResult<&[u8], Loop> {
+  let (input, (_, index, start_ms, end_ms, _, _, color, locked, name)) =
+    tuple((tag(b"\x00"), be_u8, be_u32, be_u32, tag(b"\xff\xff\xff\xff"), tag(b"\x00"), rgb, be_u8, c_string))(input)?;
+  Ok((input, Loop { index, start_ms, end_ms, color, locked: locked != 0, name }))
+}
+
+impl BeatGrid {
+  pub fn parse(data: &[u8]) -> Result<BeatGrid> {
+    let (_, grid) = beatgrid(data).map_err(|_| "Invalid Serato BeatGrid")?;
+    Ok(grid)
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut out = vec![1u8, 0];
+    out.extend_from_slice(&(self.markers.len() as u32).to_be_bytes());
+    for marker in &self.markers {
+      match marker {
+        BeatGridMarker::NonTerminal { position, beats_till_next } => {
+          out.extend_from_slice(&position.to_be_bytes());
+          out.extend_from_slice(&beats_till_next.to_be_bytes());
+        }
+        BeatGridMarker::Terminal { position, bpm } => {
+          out.extend_from_slice(&position.to_be_bytes());
+          out.extend_from_slice(&bpm.to_be_bytes());
+        }
+      }
+    }
+    out.push(self.footer);
+    out
+  }
+
+  pub fn bpm(&self) -> Option<f32> {
+    self.markers.iter().find_map(|m| match m {
+      BeatGridMarker::Terminal { bpm, .. } => Some(*bpm),
+      _ => None
+    })
+  }
+}
+
+fn beatgrid(input: &[u8]) -> IResult<&[u8], BeatGrid> {
+  let (input, (_, len)) = tuple((tag(b"\x01\x00"), be_u32))(input)?;
+  let non_terminal = len.saturating_sub(1) as usize;
+  let (input, mut markers) = count(nonterminal_marker, non_terminal)(input)?;
+  let (input, terminal) = count(terminal_marker, (len > 0) as usize)(input)?;
+  markers.extend(terminal);
+  let (input, footer) = be_u8(input)?;
+  Ok((input, BeatGrid { markers, footer }))
+}
+
+fn nonterminal_marker(input: &[u8]) -> IResult<&[u8], BeatGridMarker> {
+  let (input, (position, beats_till_next)) = tuple((be_f32, be_u32))(input)?;
+  Ok((input, BeatGridMarker::NonTerminal { position, beats_till_next }))
+}
+
+fn terminal_marker(input: &[u8]) -> IResult<&[u8], BeatGridMarker> {
+  let (input, (position, bpm)) = tuple((be_f32, be_f32))(input)?;
+  Ok((input, BeatGridMarker::Terminal { position, bpm }))
+}
+
+impl Autotags {
+  pub fn parse(data: &[u8]) -> Result<Autotags> {
+    let (_, (_, bpm, auto_gain, gain_db)) = tuple((tag(b"\x01\x01"), c_string, c_string, c_string))(data)
+      .map_err(|_: nom::Err<nom::error::Error<&[u8]>>| "Invalid Serato Autotags")?;
+    Ok(Autotags { bpm: bpm.parse()?, auto_gain: auto_gain.parse()?, gain_db: gain_db.parse()? })
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    format!("\x01\x01{:.2}\x00{:.3}\x00{:.3}\x00", self.bpm, self.auto_gain, self.gain_db).into_bytes()
+  }
+}
+
+impl Overview {
+  pub fn parse(data: &[u8]) -> Result<Overview> {
+    let blocks = data.strip_prefix(b"\x01\x05").ok_or("Invalid Serato Overview version")?;
+    if blocks.len() % OVERVIEW_BLOCK != 0 {
+      Err("Invalid Serato Overview length")?
+    }
+    let blocks = blocks.chunks(OVERVIEW_BLOCK).map(|block| block.try_into().unwrap()).collect();
+    Ok(Overview { blocks })
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut out = vec![1u8, 5];
+    out.extend(self.blocks.iter().flatten());
+    out
+  }
+}
+
+impl Analysis {
+  pub fn parse(data: &[u8]) -> Result<Analysis> {
+    let (_, (major, minor)) = tuple((be_u8, be_u8))(data)
+      .map_err(|_: nom::Err<nom::error::Error<&[u8]>>| "Invalid Serato Analysis")?;
+    Ok(Analysis { major, minor })
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    vec![self.major, self.minor]
+  }
+}
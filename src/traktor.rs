@@ -0,0 +1,261 @@
+use nom::bytes::complete::take;
+use nom::multi::count;
+use nom::number::complete::{le_f64, le_i32, le_u16, le_u32};
+use nom::sequence::tuple;
+use nom::IResult;
+
+use crate::musical_key::MusicalKey;
+use crate::{Result, Tag};
+
+// Traktor stores its analysis in a PRIV frame as a tree of chunks, each with a reversed four
+// character id, the little endian length of its data and the number of child chunks.
+
+pub static TRAKTOR_OWNER: &str = "TRAKTOR4";
+
+pub static CUE_POINTS_CHUNK: &str = "CUEP";
+pub static KEY_CHUNK: &str = "MKEY";
+pub static BPM_CHUNK: &str = "HBPM";
+
+const CHUNK_HEADER: usize = 12;
+/// Deeper than the real tree, chunks below it are kept as data
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+  /// In reading order, the bytes are stored reversed
+  pub id: [u8; 4],
+  pub data: Vec<u8>,
+  pub children: Vec<Chunk>,
+  /// As stored, written back for chunks whose data did not parse as children
+  pub child_count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CueKind {
+  Cue,
+  FadeIn,
+  FadeOut,
+  Load,
+  Grid,
+  Loop,
+  Unknown(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+  pub name: String,
+  pub kind: CueKind,
+  pub start_ms: f64,
+  pub length_ms: f64,
+  pub repeats: i32,
+  /// -1 when the cue is not assigned to a hotcue button
+  pub hotcue: i32,
+  pub display_order: u32,
+  pub flags: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Traktor {
+  pub root: Chunk,
+  /// Zero padding following the chunk tree
+  pub padding: Vec<u8>,
+}
+
+impl From<u32> for CueKind {
+  fn from(kind: u32) -> CueKind {
+    match kind {
+      0 => CueKind::Cue,
+      1 => CueKind::FadeIn,
+      2 => CueKind::FadeOut,
+      3 => CueKind::Load,
+      4 => CueKind::Grid,
+      5 => CueKind::Loop,
+      n => CueKind::Unknown(n),
+    }
+  }
+}
+
+impl From<CueKind> for u32 {
+  fn from(kind: CueKind) -> u32 {
+    match kind {
+      CueKind::Cue => 0,
+      CueKind::FadeIn => 1,
+      CueKind::FadeOut => 2,
+      CueKind::Load => 3,
+      CueKind::Grid => 4,
+      CueKind::Loop => 5,
+      CueKind::Unknown(n) => n,
+    }
+  }
+}
+
+impl Cue {
+  pub fn new(name: &str, kind: CueKind, start_ms: f64, length_ms: f64, hotcue: i32) -> Cue {
+    Cue { name: name.to_string(), kind, start_ms, length_ms, repeats: -1, hotcue, display_order: 0, flags: 1 }
+  }
+}
+
+impl Traktor {
+  pub fn read(tag: &Tag) -> Result<Option<Traktor>> {
+    tag.private(TRAKTOR_OWNER).map(Traktor::parse).transpose()
+  }
+
+  pub fn write(&self, tag: &mut Tag) {
+    tag.set_private(TRAKTOR_OWNER, &self.to_bytes());
+  }
+
+  pub fn parse(data: &[u8]) -> Result<Traktor> {
+    let (padding, root) = chunk(data, 0).map_err(|_| "Invalid Traktor chunk")?;
+    Ok(Traktor { root, padding: padding.to_vec() })
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut out = self.root.to_bytes();
+    out.extend_from_slice(&self.padding);
+    out
+  }
+
+  pub fn find(&self, id: &str) -> Option<&Chunk> {
+    self.root.find(id)
+  }
+
+  pub fn all_cues(&self) -> Result<Vec<Cue>> {
+    match self.find(CUE_POINTS_CHUNK) {
+      Some(chunk) => {
+        let (_, cues) = cue_points(&chunk.data).map_err(|_| "Invalid Traktor cue points")?;
+        Ok(cues)
+      }
+      None => Ok(vec![])
+    }
+  }
+
+  /// Cue points that are neither grid markers nor loops
+  pub fn cues(&self) -> Result<Vec<Cue>> {
+    self.cues_where(|kind| !matches!(kind, CueKind::Grid | CueKind::Loop))
+  }
+
+  pub fn grid_markers(&self) -> Result<Vec<Cue>> {
+    self.cues_where(|kind| kind == CueKind::Grid)
+  }
+
+  pub fn loops(&self) -> Result<Vec<Cue>> {
+    self.cues_where(|kind| kind == CueKind::Loop)
+  }
+
+  fn cues_where(&self, predicate: impl Fn(CueKind) -> bool) -> Result<Vec<Cue>> {
+    Ok(self.all_cues()?.into_iter().filter(|cue| predicate(cue.kind)).collect())
+  }
+
+  /// Replaces all cue points, including grid markers and loops
+  pub fn set_cues(&mut self, cues: &[Cue]) -> Result<()> {
+    let chunk = self.root.find_mut(CUE_POINTS_CHUNK).ok_or("No Traktor cue points chunk")?;
+    chunk.data = cue_points_to_bytes(cues);
+    Ok(())
+  }
+
+  pub fn key(&self) -> Option<MusicalKey> {
+    let index = self.find(KEY_CHUNK).and_then(|c| c.data.get(..4))
+      .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))?;
+    match index {
+      0..=11 => Some(MusicalKey::Major(index as u8)),
+      12..=23 => Some(MusicalKey::Minor(index as u8 - 12)),
+      _ => None
+    }
+  }
+
+  pub fn set_key(&mut self, key: MusicalKey) -> Result<()> {
+    let index = match key {
+      MusicalKey::Major(pitch) => pitch as u32,
+      MusicalKey::Minor(pitch) => pitch as u32 + 12,
+      MusicalKey::OffKey => Err("Traktor has no off-key")?,
+    };
+    let chunk = self.root.find_mut(KEY_CHUNK).ok_or("No Traktor key chunk")?;
+    chunk.data = index.to_le_bytes().to_vec();
+    Ok(())
+  }
+
+  pub fn bpm(&self) -> Option<f32> {
+    self.find(BPM_CHUNK).and_then(|c| c.data.get(..4))
+      .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+  }
+}
+
+impl Chunk {
+  pub fn find(&self, id: &str) -> Option<&Chunk> {
+    if self.id == id.as_bytes() {
+      return Some(self);
+    }
+    self.children.iter().find_map(|child| child.find(id))
+  }
+
+  fn find_mut(&mut self, id: &str) -> Option<&mut Chunk> {
+    if self.id == id.as_bytes() {
+      return Some(self);
+    }
+    self.children.iter_mut().find_map(|child| child.find_mut(id))
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let (data, child_count) = if self.children.is_empty() {
+      (self.data.clone(), self.child_count)
+    } else {
+      (self.children.iter().flat_map(|child| child.to_bytes()).collect(), self.children.len() as u32)
+    };
+    let mut out = Vec::with_capacity(CHUNK_HEADER + data.len());
+    out.extend(self.id.iter().rev());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&child_count.to_le_bytes());
+    out.extend_from_slice(&data);
+    out
+  }
+}
+
+fn chunk(input: &[u8], depth: usize) -> IResult<&[u8], Chunk> {
+  let (input, (id, len, child_count)) = tuple((take(4usize), le_u32, le_u32))(input)?;
+  let (input, data) = take(len)(input)?;
+  let id = [id[3], id[2], id[1], id[0]];
+  let children = if depth == MAX_DEPTH {
+    vec![]
+  } else {
+    match count(|input| chunk(input, depth + 1), child_count as usize)(data) {
+      Ok((&[], children)) => children,
+      _ => vec![], // not a container after all, keep the bytes as they are
+    }
+  };
+  let data = if children.is_empty() { data.to_vec() } else { vec![] };
+  Ok((input, Chunk { id, data, children, child_count }))
+}
+
+fn utf16_string(input: &[u8]) -> IResult<&[u8], String> {
+  let (input, len) = le_u32(input)?;
+  let (input, words) = count(le_u16, len as usize)(input)?;
+  Ok((input, String::from_utf16_lossy(&words)))
+}
+
+fn cue_point(input: &[u8]) -> IResult<&[u8], Cue> {
+  let (input, (flags, name, display_order, kind, start_ms, length_ms, repeats, hotcue)) =
+    tuple((le_u32, utf16_string, le_u32, le_u32, le_f64, le_f64, le_i32, le_i32))(input)?;
+  Ok((input, Cue { name, kind: CueKind::from(kind), start_ms, length_ms, repeats, hotcue, display_order, flags }))
+}
+
+fn cue_points(input: &[u8]) -> IResult<&[u8], Vec<Cue>> {
+  let (input, len) = le_u32(input)?;
+  count(cue_point, len as usize)(input)
+}
+
+fn cue_points_to_bytes(cues: &[Cue]) -> Vec<u8> {
+  let mut out = (cues.len() as u32).to_le_bytes().to_vec();
+  for cue in cues {
+    let name: Vec<u16> = cue.name.encode_utf16().collect();
+    out.extend_from_slice(&cue.flags.to_le_bytes());
+    out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    out.extend(name.iter().flat_map(|w| w.to_le_bytes()));
+    out.extend_from_slice(&cue.display_order.to_le_bytes());
+    out.extend_from_slice(&u32::from(cue.kind).to_le_bytes());
+    out.extend_from_slice(&cue.start_ms.to_le_bytes());
+    out.extend_from_slice(&cue.length_ms.to_le_bytes());
+    out.extend_from_slice(&cue.repeats.to_le_bytes());
+    out.extend_from_slice(&cue.hotcue.to_le_bytes());
+  }
+  out
+}
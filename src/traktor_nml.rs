@@ -0,0 +1,549 @@
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::cue_points::{BeatGrid, CuePoint, Cues, GridMarker, Loop, Vendor};
+use crate::musical_key::MusicalKey;
+use crate::traktor::{Cue, CueKind};
+use crate::ID3rs;
+use crate::Result;
+
+// Traktor keeps its collection in collection.nml. The document is kept as an element tree with the
+// text between elements as is, so writing it back only changes the attributes that were set.
+
+pub static RATING_AUTHOR: &str = "traktor@native-instruments.de";
+
+const RATING_STEP: u8 = 51;
+const PATH_SEPARATOR: &str = "/:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+  Element(Element),
+  /// Text, comments and whitespace, still escaped
+  Raw(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+  pub name: String,
+  pub attributes: Vec<(String, String)>,
+  pub children: Vec<Node>,
+  /// Written as `<NAME/>` when it has no children
+  pub empty: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nml {
+  prolog: String,
+  pub root: Element,
+  epilog: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+  Title,
+  Artist,
+  Album,
+  Genre,
+  Rating,
+  Bpm,
+  Key,
+  Cues,
+}
+
+pub static FIELDS: [Field; 8] = [Field::Title, Field::Artist, Field::Album, Field::Genre, Field::Rating, Field::Bpm, Field::Key, Field::Cues];
+
+/// What Traktor knows about a track, in the collection or in its tag
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Entry {
+  pub path: Option<PathBuf>,
+  pub title: Option<String>,
+  pub artist: Option<String>,
+  pub album: Option<String>,
+  pub genre: Option<String>,
+  /// Stars from 0 to 5
+  pub rating: Option<u8>,
+  pub bpm: Option<f64>,
+  pub key: Option<MusicalKey>,
+  /// Cue points, loops and grid markers, positions in milliseconds
+  pub cues: Option<Vec<Cue>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+  /// The collection wins
+  ToTags,
+  /// The tags win
+  ToCollection,
+  /// Values missing on one side are copied, differing values are reported as conflicts
+  TwoWay,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+  pub path: PathBuf,
+  pub field: Field,
+  pub collection: String,
+  pub tag: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SyncReport {
+  pub to_tags: Vec<(PathBuf, Field)>,
+  pub to_collection: Vec<(PathBuf, Field)>,
+  pub conflicts: Vec<Conflict>,
+  /// Entries of which the file could not be found
+  pub missing: Vec<PathBuf>,
+  /// Entries of which the tag could not be read or written
+  pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Element {
+  pub fn new(name: &str) -> Element {
+    Element { name: name.to_string(), attributes: vec![], children: vec![], empty: false }
+  }
+
+  pub fn attribute(&self, name: &str) -> Option<&str> {
+    self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+  }
+
+  pub fn set_attribute(&mut self, name: &str, value: &str) {
+    match self.attributes.iter_mut().find(|(key, _)| key == name) {
+      Some((_, existing)) => *existing = value.to_string(),
+      None => self.attributes.push((name.to_string(), value.to_string())),
+    }
+  }
+
+  pub fn elements<'a>(&'a self, name: &'a str) -> impl Iterator<Item=&'a Element> {
+    self.children.iter().filter_map(move |node| match node {
+      Node::Element(element) if element.name == name => Some(element),
+      _ => None
+    })
+  }
+
+  pub fn element(&self, name: &str) -> Option<&Element> {
+    self.children.iter().find_map(|node| match node {
+      Node::Element(element) if element.name == name => Some(element),
+      _ => None
+    })
+  }
+
+  fn element_mut(&mut self, name: &str) -> &mut Element {
+    let index = self.children.iter().position(|node| matches!(node, Node::Element(e) if e.name == name));
+    let index = match index {
+      Some(index) => index,
+      None => {
+        self.children.push(Node::Element(Element::new(name)));
+        self.children.len() - 1
+      }
+    };
+    match &mut self.children[index] {
+      Node::Element(element) => element,
+      Node::Raw(_) => unreachable!(),
+    }
+  }
+
+  fn elements_mut<'a>(&'a mut self, name: &'a str) -> impl Iterator<Item=&'a mut Element> {
+    self.children.iter_mut().filter_map(move |node| match node {
+      Node::Element(element) if element.name == name => Some(element),
+      _ => None
+    })
+  }
+
+  fn write(&self, out: &mut String) {
+    out.push('<');
+    out.push_str(&self.name);
+    for (key, value) in &self.attributes {
+      out.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+    }
+    if self.empty && self.children.is_empty() {
+      out.push_str("/>");
+      return;
+    }
+    out.push('>');
+    for child in &self.children {
+      match child {
+        Node::Element(element) => element.write(out),
+        Node::Raw(raw) => out.push_str(raw),
+      }
+    }
+    out.push_str(&format!("</{}>", self.name));
+  }
+}
+
+impl Nml {
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Nml> {
+    Nml::parse(&fs::read_to_string(path)?)
+  }
+
+  pub fn parse(xml: &str) -> Result<Nml> {
+    let mut reader = Reader::from_str(xml);
+    let mut stack: Vec<Element> = vec![];
+    let mut prolog = None;
+    loop {
+      let before = reader.buffer_position() as usize;
+      let event = reader.read_event()?;
+      let after = reader.buffer_position() as usize;
+      match event {
+        Event::Start(start) => {
+          prolog.get_or_insert_with(|| xml[..before].to_string());
+          stack.push(element(&start, false)?);
+        }
+        Event::Empty(start) => {
+          prolog.get_or_insert_with(|| xml[..before].to_string());
+          let element = element(&start, true)?;
+          match stack.last_mut() {
+            Some(parent) => parent.children.push(Node::Element(element)),
+            None => return Ok(Nml { prolog: prolog.unwrap_or_default(), root: element, epilog: xml[after..].to_string() }),
+          }
+        }
+        Event::End(_) => {
+          let element = stack.pop().ok_or("Unbalanced NML")?;
+          match stack.last_mut() {
+            Some(parent) => parent.children.push(Node::Element(element)),
+            None => return Ok(Nml { prolog: prolog.unwrap_or_default(), root: element, epilog: xml[after..].to_string() }),
+          }
+        }
+        Event::Eof => Err("No NML root element")?,
+        _ => {
+          if let Some(parent) = stack.last_mut() {
+            parent.children.push(Node::Raw(xml[before..after].to_string()));
+          }
+        }
+      }
+    }
+  }
+
+  pub fn to_xml(&self) -> String {
+    let mut out = self.prolog.clone();
+    self.root.write(&mut out);
+    out.push_str(&self.epilog);
+    out
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    Ok(fs::write(path, self.to_xml())?)
+  }
+
+  fn collection(&self) -> Option<&Element> {
+    self.root.element("COLLECTION")
+  }
+
+  pub fn entries(&self) -> Vec<Entry> {
+    self.collection()
+      .map(|collection| collection.elements("ENTRY").map(Entry::from_element).collect())
+      .unwrap_or_default()
+  }
+
+  /// The collection entry for `path`
+  pub fn entry<P: AsRef<Path>>(&self, path: P) -> Option<Entry> {
+    let path = canonical(path.as_ref());
+    self.entries().into_iter().find(|entry| entry.path.as_deref().map(canonical) == Some(path.clone()))
+  }
+
+  /// Writes the `fields` of `entry` to the collection entry with the same path, returns whether there was one
+  pub fn set_entry(&mut self, entry: &Entry, fields: &[Field]) -> bool {
+    let path = match &entry.path {
+      Some(path) => canonical(path),
+      None => return false
+    };
+    let collection = self.root.element_mut("COLLECTION");
+    match collection.elements_mut("ENTRY").find(|e| location(e).as_deref().map(canonical) == Some(path.clone())) {
+      Some(element) => {
+        entry.to_element(element, fields);
+        true
+      }
+      None => false
+    }
+  }
+
+  /// Synchronises the collection with the tags of the files it refers to, writing changed tags.
+  /// Cues are written to the tag for `vendors`, entries that fail are reported and skipped.
+  pub fn sync(&mut self, mode: SyncMode, vendors: &[Vendor]) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    for entry in self.entries() {
+      let path = match &entry.path {
+        Some(path) if path.exists() => path.clone(),
+        Some(path) => {
+          report.missing.push(path.clone());
+          continue;
+        }
+        None => continue
+      };
+      if let Err(e) = self.sync_entry(entry, &path, mode, vendors, &mut report) {
+        report.errors.push((path, e.to_string()));
+      }
+    }
+    Ok(report)
+  }
+
+  fn sync_entry(&mut self, mut entry: Entry, path: &Path, mode: SyncMode, vendors: &[Vendor], report: &mut SyncReport) -> Result<()> {
+    let mut tag = ID3rs::read(path)?;
+    let mut from_tag = Entry::from_tag(&tag)?;
+
+    let mut to_tag = vec![];
+    let mut to_collection = vec![];
+    for field in FIELDS {
+      match entry.merge(&mut from_tag, field, mode) {
+        Merge::ToTag => to_tag.push(field),
+        Merge::ToCollection => to_collection.push(field),
+        Merge::Conflict(collection, tag) => report.conflicts.push(Conflict { path: path.to_path_buf(), field, collection, tag }),
+        Merge::Same => {}
+      }
+    }
+    if !to_tag.is_empty() {
+      from_tag.apply(&mut tag, &to_tag, vendors)?;
+      tag.write()?;
+    }
+    if !to_collection.is_empty() {
+      entry.path = Some(path.to_path_buf());
+      self.set_entry(&entry, &to_collection);
+    }
+    report.to_tags.extend(to_tag.into_iter().map(|field| (path.to_path_buf(), field)));
+    report.to_collection.extend(to_collection.into_iter().map(|field| (path.to_path_buf(), field)));
+    Ok(())
+  }
+}
+
+enum Merge {
+  Same,
+  ToTag,
+  ToCollection,
+  Conflict(String, String),
+}
+
+impl Entry {
+  fn from_element(element: &Element) -> Entry {
+    let info = element.element("INFO");
+    let text = |value: Option<&str>| value.filter(|v| !v.is_empty()).map(str::to_string);
+    let key = element.element("MUSICAL_KEY").and_then(|k| k.attribute("VALUE")).and_then(|v| v.parse().ok())
+      .and_then(key_from_index)
+      .or_else(|| info.and_then(|i| i.attribute("KEY")).and_then(MusicalKey::parse));
+    let cues: Vec<Cue> = element.elements("CUE_V2").filter_map(cue_from_element).collect();
+    Entry {
+      path: location(element),
+      title: text(element.attribute("TITLE")),
+      artist: text(element.attribute("ARTIST")),
+      album: text(element.element("ALBUM").and_then(|a| a.attribute("TITLE"))),
+      genre: text(info.and_then(|i| i.attribute("GENRE"))),
+      rating: info.and_then(|i| i.attribute("RANKING")).and_then(|r| r.parse::<u8>().ok()).map(|r| r / RATING_STEP),
+      bpm: element.element("TEMPO").and_then(|t| t.attribute("BPM")).and_then(|b| b.parse().ok()),
+      key,
+      cues: if cues.is_empty() { None } else { Some(cues) },
+    }
+  }
+
+  fn to_element(&self, element: &mut Element, fields: &[Field]) {
+    for field in fields {
+      match field {
+        Field::Title => set_optional(element, "TITLE", &self.title),
+        Field::Artist => set_optional(element, "ARTIST", &self.artist),
+        Field::Album => set_optional(element.element_mut("ALBUM"), "TITLE", &self.album),
+        Field::Genre => set_optional(element.element_mut("INFO"), "GENRE", &self.genre),
+        Field::Rating => if let Some(rating) = self.rating {
+          element.element_mut("INFO").set_attribute("RANKING", &(rating.min(5) * RATING_STEP).to_string());
+        },
+        Field::Bpm => if let Some(bpm) = self.bpm {
+          element.element_mut("TEMPO").set_attribute("BPM", &format!("{:.6}", bpm));
+        },
+        Field::Key => if let Some((key, index)) = self.key.and_then(|key| Some((key, key_to_index(key)?))) {
+          element.element_mut("MUSICAL_KEY").set_attribute("VALUE", &index.to_string());
+          if let Some(open_key) = key.open_key() {
+            element.element_mut("INFO").set_attribute("KEY", &open_key);
+          }
+        },
+        Field::Cues => if let Some(cues) = &self.cues {
+          element.children.retain(|node| !matches!(node, Node::Element(e) if e.name == "CUE_V2"));
+          element.children.extend(cues.iter().map(|cue| Node::Element(cue_to_element(cue))));
+        },
+      }
+    }
+  }
+
+  pub fn from_tag(tag: &ID3rs) -> Result<Entry> {
+    let rating = tag.popularity(RATING_AUTHOR)
+      .or_else(|| tag.popularities().into_iter().max_by_key(|(_, rating)| *rating))
+      .map(|(_, rating)| rating);
+    let cues = tag.cues()?;
+    let bpm = tag.bpm().or_else(|| cues.beat_grid.as_ref().and_then(|grid| grid.markers.first()).map(|m| m.bpm));
+    Ok(Entry {
+      path: Some(tag.path.clone()),
+      title: tag.title().map(str::to_string),
+      artist: tag.artist().map(str::to_string),
+      album: tag.album().map(str::to_string),
+      genre: tag.genre().map(str::to_string),
+      rating,
+      bpm,
+      key: tag.musical_key(),
+      cues: if cues.is_empty() { None } else { Some(traktor_cues(&cues)) },
+    })
+  }
+
+  /// Writes the `fields` to the tag, cues go to `vendors`
+  pub fn apply(&self, tag: &mut ID3rs, fields: &[Field], vendors: &[Vendor]) -> Result<()> {
+    for field in fields {
+      match field {
+        Field::Title => if let Some(title) = &self.title { tag.set_title(title) },
+        Field::Artist => if let Some(artist) = &self.artist { tag.set_artist(artist) },
+        Field::Album => if let Some(album) = &self.album { tag.set_album(album) },
+        Field::Genre => if let Some(genre) = &self.genre { tag.set_genre(genre) },
+        Field::Rating => if let Some(rating) = self.rating { tag.set_popularity(RATING_AUTHOR, rating.min(5)) },
+        Field::Bpm => if let Some(bpm) = self.bpm { tag.set_bpm(bpm) },
+        Field::Key => if let Some(key) = self.key { tag.set_key(&key.standard()) },
+        Field::Cues => if let Some(cues) = &self.cues { tag.set_cues(&neutral_cues(cues, self.bpm), vendors)? },
+      }
+    }
+    Ok(())
+  }
+
+  /// Cue points, loops and beat grid in the vendor-neutral model
+  pub fn neutral_cues(&self) -> Cues {
+    neutral_cues(self.cues.as_deref().unwrap_or_default(), self.bpm)
+  }
+
+  fn merge(&mut self, tag: &mut Entry, field: Field, mode: SyncMode) -> Merge {
+    match field {
+      Field::Title => merge(&mut self.title, &mut tag.title, mode, |a, b| a == b),
+      Field::Artist => merge(&mut self.artist, &mut tag.artist, mode, |a, b| a == b),
+      Field::Album => merge(&mut self.album, &mut tag.album, mode, |a, b| a == b),
+      Field::Genre => merge(&mut self.genre, &mut tag.genre, mode, |a, b| a == b),
+      Field::Rating => merge(&mut self.rating, &mut tag.rating, mode, |a, b| a == b),
+      // TBPM only holds whole beats per minute
+      Field::Bpm => merge(&mut self.bpm, &mut tag.bpm, mode, |a, b| a.round() == b.round()),
+      Field::Key => merge(&mut self.key, &mut tag.key, mode, |a, b| a == b),
+      Field::Cues => merge(&mut self.cues, &mut tag.cues, mode, same_cues),
+    }
+  }
+}
+
+fn merge<T: Clone + Debug>(collection: &mut Option<T>, tag: &mut Option<T>, mode: SyncMode, same: impl Fn(&T, &T) -> bool) -> Merge {
+  match (collection.as_ref(), tag.as_ref(), mode) {
+    (Some(a), Some(b), _) if same(a, b) => Merge::Same,
+    (None, None, _) => Merge::Same,
+    (Some(a), Some(b), SyncMode::TwoWay) => Merge::Conflict(format!("{:?}", a), format!("{:?}", b)),
+    (Some(_), _, SyncMode::ToTags) | (Some(_), None, SyncMode::TwoWay) => {
+      *tag = collection.clone();
+      Merge::ToTag
+    }
+    (_, Some(_), SyncMode::ToCollection) | (None, Some(_), SyncMode::TwoWay) => {
+      *collection = tag.clone();
+      Merge::ToCollection
+    }
+    _ => Merge::Same
+  }
+}
+
+/// Same kind, hotcue and position to the millisecond
+fn same_cues(a: &Vec<Cue>, b: &Vec<Cue>) -> bool {
+  let key = |cues: &Vec<Cue>| {
+    let mut keys: Vec<(u32, i32, i64, i64)> = cues.iter()
+      .map(|c| (u32::from(c.kind), c.hotcue, c.start_ms.round() as i64, c.length_ms.round() as i64))
+      .collect();
+    keys.sort();
+    keys
+  };
+  key(a) == key(b)
+}
+
+fn traktor_cues(cues: &Cues) -> Vec<Cue> {
+  let hotcue = |index: Option<u8>| index.map(|i| i as i32).unwrap_or(-1);
+  let mut result: Vec<Cue> = cues.beat_grid.iter().flat_map(|grid| grid.markers.iter())
+    .map(|marker| Cue::new("AutoGrid", CueKind::Grid, marker.position_ms, 0.0, -1))
+    .collect();
+  result.extend(cues.cue_points.iter().map(|cue| Cue::new(&cue.name, CueKind::Cue, cue.position_ms, 0.0, hotcue(cue.index))));
+  result.extend(cues.loops.iter().map(|lp| Cue::new(&lp.name, CueKind::Loop, lp.start_ms, lp.end_ms - lp.start_ms, hotcue(lp.index))));
+  result
+}
+
+fn neutral_cues(cues: &[Cue], bpm: Option<f64>) -> Cues {
+  let index = |hotcue: i32| u8::try_from(hotcue).ok();
+  let mut result = Cues::default();
+  for cue in cues {
+    match cue.kind {
+      CueKind::Grid => result.beat_grid.get_or_insert_with(BeatGrid::default).markers
+        .push(GridMarker { position_ms: cue.start_ms, bpm: bpm.unwrap_or_default() }),
+      CueKind::Loop => result.loops.push(Loop::new(index(cue.hotcue), cue.start_ms, cue.start_ms + cue.length_ms, &cue.name)),
+      _ => result.cue_points.push(CuePoint::new(index(cue.hotcue), cue.start_ms, &cue.name)),
+    }
+  }
+  result
+}
+
+fn key_from_index(index: u32) -> Option<MusicalKey> {
+  match index {
+    0..=11 => Some(MusicalKey::Major(index as u8)),
+    12..=23 => Some(MusicalKey::Minor(index as u8 - 12)),
+    _ => None
+  }
+}
+
+fn key_to_index(key: MusicalKey) -> Option<u32> {
+  match key {
+    MusicalKey::Major(pitch) => Some(pitch as u32),
+    MusicalKey::Minor(pitch) => Some(pitch as u32 + 12),
+    MusicalKey::OffKey => None,
+  }
+}
+
+fn cue_from_element(element: &Element) -> Option<Cue> {
+  let number = |name: &str| element.attribute(name).and_then(|v| v.parse::<f64>().ok());
+  Some(Cue {
+    name: element.attribute("NAME").unwrap_or_default().to_string(),
+    kind: CueKind::from(number("TYPE")? as u32),
+    start_ms: number("START")?,
+    length_ms: number("LEN").unwrap_or_default(),
+    repeats: number("REPEATS").unwrap_or(-1.0) as i32,
+    hotcue: number("HOTCUE").unwrap_or(-1.0) as i32,
+    display_order: number("DISPL_ORDER").unwrap_or_default() as u32,
+    flags: 1,
+  })
+}
+
+fn cue_to_element(cue: &Cue) -> Element {
+  let mut element = Element::new("CUE_V2");
+  element.set_attribute("NAME", &cue.name);
+  element.set_attribute("DISPL_ORDER", &cue.display_order.to_string());
+  element.set_attribute("TYPE", &u32::from(cue.kind).to_string());
+  element.set_attribute("START", &format!("{:.6}", cue.start_ms));
+  element.set_attribute("LEN", &format!("{:.6}", cue.length_ms));
+  element.set_attribute("REPEATS", &cue.repeats.to_string());
+  element.set_attribute("HOTCUE", &cue.hotcue.to_string());
+  element
+}
+
+fn set_optional(element: &mut Element, name: &str, value: &Option<String>) {
+  if let Some(value) = value {
+    element.set_attribute(name, value);
+  }
+}
+
+/// `DIR` separates folders with `/:`, a Windows `VOLUME` is the drive letter
+fn location(entry: &Element) -> Option<PathBuf> {
+  let location = entry.element("LOCATION")?;
+  let dir = location.attribute("DIR")?.replace(PATH_SEPARATOR, "/");
+  let file = location.attribute("FILE")?;
+  let volume = location.attribute("VOLUME").filter(|v| v.len() == 2 && v.ends_with(':')).unwrap_or_default();
+  Some(PathBuf::from(format!("{}{}{}", volume, dir, file)))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+  path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn element(start: &BytesStart, empty: bool) -> Result<Element> {
+  let name = String::from_utf8(start.name().as_ref().to_vec())?;
+  let attributes = start.attributes()
+    .map(|attribute| {
+      let attribute = attribute?;
+      Ok((String::from_utf8(attribute.key.as_ref().to_vec())?, attribute.unescape_value()?.to_string()))
+    })
+    .collect::<Result<Vec<_>>>()?;
+  Ok(Element { name, attributes, children: vec![], empty })
+}
+
+fn escape(value: &str) -> String {
+  value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
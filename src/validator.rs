@@ -0,0 +1,148 @@
+// Checks the MPEG audio stream following the tag, like mpck does
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::mp3_frame::{frame_header, Layer, Version};
+use crate::vbr_header::VbrKind;
+use crate::{ID3rs, Result, ID3HEADER_SIZE};
+
+/// ID3v1 tag at the end of the file
+const ID3V1_SIZE: usize = 128;
+
+/// Offsets count from the first byte after the ID3v2 tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+  /// Bytes that are not part of a frame, where sync was lost
+  Junk { offset: u64, len: u64 },
+  /// The last frame runs past the end of the file
+  Truncated { offset: u64, len: u64, expected: u64 },
+  /// Version, layer or sample rate differ from the first frame
+  Inconsistent { offset: u64 },
+  /// Consecutive frames whose CRC-16 does not match
+  BadCrc { offset: u64, len: u64 },
+  /// Frames counted in the Xing/Info or VBRI header differ from the frames found
+  FrameCount { header: u32, found: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+  pub version: Option<Version>,
+  pub layer: Option<Layer>,
+  pub sample_rate: Option<u32>,
+  /// Frames found, including a Xing/Info or VBRI frame
+  pub frames: u32,
+  /// Frames with a CRC-16
+  pub protected_frames: u32,
+  /// Protected frames whose CRC-16 does not match
+  pub corrupt_frames: u32,
+  pub vbr_header: Option<VbrKind>,
+  pub id3v1: bool,
+  pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+  pub fn read(tag: &ID3rs) -> Result<ValidationReport> {
+    let mut file = File::open(&tag.path)?;
+    if tag.header_size > 0 {
+      file.seek(SeekFrom::Start(ID3HEADER_SIZE + tag.header_size))?;
+    }
+    let mut audio = vec![];
+    file.read_to_end(&mut audio)?;
+    Ok(Self::parse(&audio))
+  }
+
+  /// Walks all frames in `audio`, the data after the ID3v2 tag
+  pub fn parse(audio: &[u8]) -> ValidationReport {
+    let id3v1 = audio.len() >= ID3V1_SIZE && audio[audio.len() - ID3V1_SIZE..].starts_with(b"TAG");
+    let audio = if id3v1 { &audio[..audio.len() - ID3V1_SIZE] } else { audio };
+    let mut report = ValidationReport { version: None, layer: None, sample_rate: None, frames: 0, protected_frames: 0, corrupt_frames: 0, vbr_header: None, id3v1, issues: vec![] };
+    let mut header_frames = None;
+    let mut position = 0;
+    while position < audio.len() {
+      let Some(sync) = find_sync(&audio[position..]).map(|sync| position + sync) else {
+        report.junk(position, audio.len());
+        break;
+      };
+      let frame = match frame_header(&audio[sync..]) {
+        Ok((_, frame)) => frame,
+        Err(nom::Err::Incomplete(_)) => {
+          report.junk(position, sync);
+          let expected = expected_size(&audio[sync..]).unwrap_or(0);
+          report.issues.push(Issue::Truncated { offset: sync as u64, len: (audio.len() - sync) as u64, expected });
+          break;
+        }
+        Err(_) => {
+          // false sync, keep looking after it
+          let skip = find_sync(&audio[sync + 1..]).map_or(audio.len(), |next| sync + 1 + next);
+          report.junk(position, skip);
+          position = skip;
+          continue;
+        }
+      };
+      report.junk(position, sync);
+      if report.frames == 0 {
+        let header = frame.vbr_header();
+        report.vbr_header = header.as_ref().map(|header| header.kind);
+        header_frames = header.and_then(|header| header.frames);
+        report.version = Some(frame.version);
+        report.layer = Some(frame.layer);
+        report.sample_rate = Some(frame.frequency);
+      } else if report.version != Some(frame.version) || report.layer != Some(frame.layer) || report.sample_rate != Some(frame.frequency) {
+        report.issues.push(Issue::Inconsistent { offset: sync as u64 });
+      }
+      match frame.crc_ok() {
+        Some(true) => report.protected_frames += 1,
+        Some(false) => report.bad_crc(sync, frame.data.len()),
+        None => {}
+      }
+      report.frames += 1;
+      position = sync + frame.data.len();
+    }
+    if let Some(header) = header_frames {
+      let found = report.frames.saturating_sub(1);
+      if header != found {
+        report.issues.push(Issue::FrameCount { header, found });
+      }
+    }
+    report
+  }
+
+  pub fn is_valid(&self) -> bool {
+    self.frames > 0 && self.issues.is_empty()
+  }
+
+  fn bad_crc(&mut self, offset: usize, len: usize) {
+    self.protected_frames += 1;
+    self.corrupt_frames += 1;
+    let (offset, len) = (offset as u64, len as u64);
+    match self.issues.last_mut() {
+      Some(Issue::BadCrc { offset: previous, len: previous_len }) if *previous + *previous_len == offset => *previous_len += len,
+      _ => self.issues.push(Issue::BadCrc { offset, len }),
+    }
+  }
+
+  fn junk(&mut self, from: usize, to: usize) {
+    if to > from {
+      self.issues.push(Issue::Junk { offset: from as u64, len: (to - from) as u64 });
+    }
+  }
+}
+
+/// Checks the MPEG audio of the file at `path`
+pub fn validate(path: impl AsRef<Path>) -> Result<ValidationReport> {
+  ValidationReport::read(&ID3rs::read(path.as_ref())?)
+}
+
+/// Offset of the next 11 set bits
+fn find_sync(input: &[u8]) -> Option<usize> {
+  input.windows(2).position(|word| word[0] == 0xff && word[1] & 0xe0 == 0xe0)
+}
+
+/// Size of a frame from its header, when the frame itself is cut off
+fn expected_size(input: &[u8]) -> Option<u64> {
+  let mut header = input.get(..4)?.to_vec();
+  header.resize(8 * 1024, 0);
+  frame_header(&header).ok().map(|(_, frame)| frame.data.len() as u64)
+}
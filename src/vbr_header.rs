@@ -0,0 +1,261 @@
+// The Xing/Info and Fraunhofer VBRI headers in the first MP3 frame
+// https://www.codeproject.com/Articles/8295/MPEG-Audio-Frame-Header#XINGHeader
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take};
+use nom::combinator::{cond, verify};
+use nom::multi::count;
+use nom::error::{Error, ErrorKind};
+use nom::number::complete::{be_i8, be_u16, be_u24, be_u32, be_u8};
+use nom::sequence::tuple;
+use nom::IResult;
+
+use crate::mp3_frame::{FrameHeader, Layer, Protection};
+
+/// Offset of the VBRI header, right after 32 bytes of side information
+const VBRI_OFFSET: usize = 4 + 32;
+const LAME_TAG_CRC_OFFSET: usize = 34;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VbrKind {
+  Xing,
+  /// A Xing header for a constant bitrate file
+  Info,
+  Vbri,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Toc {
+  /// Position at each percent of the duration, in 1/256 of the bytes
+  Xing(Vec<u8>),
+  /// Bytes of each consecutive `frames_per_entry` frames
+  Vbri { frames_per_entry: u16, sizes: Vec<u32> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VbrHeader {
+  pub kind: VbrKind,
+  /// Frames of audio, without the frame holding this header for Xing/Info
+  pub frames: Option<u32>,
+  /// Bytes of audio, including the frame holding this header
+  pub bytes: Option<u32>,
+  pub toc: Option<Toc>,
+  /// 0 (best) to 100 (worst) for Xing, the encoder setting for VBRI
+  pub quality: Option<u32>,
+  /// Follows the Xing/Info header in files encoded by LAME
+  pub lame: Option<LameTag>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VbrMethod {
+  Unknown,
+  Cbr,
+  Abr,
+  /// `--vbr-old`
+  Vbr1,
+  /// `--vbr-mtrh`, the default since LAME 3.98
+  VbrMtrh,
+  Vbr3,
+  Vbr4,
+  Cbr2Pass,
+  Abr2Pass,
+  Reserved(u8),
+}
+
+impl From<u8> for VbrMethod {
+  fn from(method: u8) -> VbrMethod {
+    match method {
+      0 => VbrMethod::Unknown,
+      1 => VbrMethod::Cbr,
+      2 => VbrMethod::Abr,
+      3 => VbrMethod::Vbr1,
+      4 => VbrMethod::VbrMtrh,
+      5 => VbrMethod::Vbr3,
+      6 => VbrMethod::Vbr4,
+      8 => VbrMethod::Cbr2Pass,
+      9 => VbrMethod::Abr2Pass,
+      method => VbrMethod::Reserved(method),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGain {
+  /// 1 set by the artist, 2 by the user, 3 by automatic analysis
+  pub originator: u8,
+  pub gain_db: f32,
+}
+
+/// The LAME extension of the Xing/Info header
+/// http://gabriel.mp3-tech.org/mp3infotag.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct LameTag {
+  /// Like `LAME3.100`
+  pub encoder: String,
+  pub revision: u8,
+  pub vbr_method: VbrMethod,
+  pub lowpass_hz: u32,
+  /// 1.0 is full scale
+  pub peak: Option<f32>,
+  pub track_gain: Option<ReplayGain>,
+  pub album_gain: Option<ReplayGain>,
+  /// `nspsytune`, `nssafejoint`, `nogap` continued and continuation in the high 4 bits
+  pub encoding_flags: u8,
+  pub ath_type: u8,
+  /// Target bitrate for ABR, minimal bitrate for VBR, in kbps, 255 means 255 or more
+  pub bitrate: u8,
+  /// Samples the encoder added at the start
+  pub delay: u16,
+  /// Samples the encoder added at the end
+  pub padding: u16,
+  pub mp3_gain: i8,
+  pub preset: u16,
+  /// Bytes of audio, including the frame holding this tag
+  pub music_length: u32,
+  pub music_crc: u16,
+  /// CRC-16 over the frame up to the stored CRC matches
+  pub crc_ok: bool,
+}
+
+impl LameTag {
+  /// The tag at `offset` in the `frame` data that starts with the frame header
+  pub fn parse(frame: &[u8], offset: usize) -> Option<LameTag> {
+    let (_, mut tag) = lame_tag(frame.get(offset..)?).ok()?;
+    tag.crc_ok = frame.get(offset + LAME_TAG_CRC_OFFSET..offset + LAME_TAG_CRC_OFFSET + 2)
+      .is_some_and(|stored| crc16(&frame[..offset + LAME_TAG_CRC_OFFSET]) == u16::from_be_bytes([stored[0], stored[1]]));
+    Some(tag)
+  }
+
+  /// Samples of the decoded audio that are no music, at both ends
+  pub fn gapless_samples(&self) -> (u16, u16) {
+    (self.delay, self.padding)
+  }
+
+  /// Like `V2`, `ABR 192` or `CBR 320`, as set with `--preset` or `-V`
+  pub fn preset_name(&self) -> Option<String> {
+    match self.preset {
+      8..=320 if matches!(self.vbr_method, VbrMethod::Cbr | VbrMethod::Cbr2Pass) => Some(format!("CBR {}", self.preset)),
+      8..=320 => Some(format!("ABR {}", self.preset)),
+      410..=500 if self.preset.is_multiple_of(10) => Some(format!("V{}", (500 - self.preset) / 10)),
+      1000 => Some("r3mix".to_string()),
+      1001 => Some("standard".to_string()),
+      1002 => Some("extreme".to_string()),
+      1003 => Some("insane".to_string()),
+      1004 => Some("standard/fast".to_string()),
+      1005 => Some("extreme/fast".to_string()),
+      1006 => Some("medium".to_string()),
+      1007 => Some("medium/fast".to_string()),
+      _ => None
+    }
+  }
+}
+
+impl VbrHeader {
+  /// The Xing/Info header after the side information of a Layer III frame, or the VBRI header
+  pub fn parse(frame: &FrameHeader) -> Option<VbrHeader> {
+    if frame.layer != Layer::Layer3 {
+      return None;
+    }
+    let crc = if frame.crc == Protection::Crc { 2 } else { 0 };
+    let xing = frame.data.get(4 + crc + frame.side_info_size()..).and_then(|data| xing_header(data).ok())
+      .map(|(rest, mut header)| {
+        header.lame = LameTag::parse(&frame.data, frame.data.len() - rest.len());
+        header
+      });
+    let vbri = || frame.data.get(VBRI_OFFSET..).and_then(|data| vbri_header(data).ok()).map(|(_, header)| header);
+    xing.or_else(vbri)
+  }
+
+  /// Byte offset from the start of the frame holding this header, for `fraction` (0.0 to 1.0) of the duration
+  pub fn seek(&self, fraction: f64) -> Option<u64> {
+    let bytes = self.bytes? as f64;
+    let fraction = fraction.clamp(0.0, 1.0);
+    match self.toc.as_ref()? {
+      Toc::Xing(toc) => {
+        let percent = (fraction * 100.0).min(99.999);
+        let index = percent as usize;
+        let before = *toc.get(index)? as f64;
+        let after = toc.get(index + 1).map_or(256.0, |after| *after as f64);
+        let position = before + (after - before) * (percent - index as f64);
+        Some((position / 256.0 * bytes) as u64)
+      }
+      Toc::Vbri { frames_per_entry, sizes } => {
+        let entries = fraction * self.frames? as f64 / *frames_per_entry as f64;
+        let whole = (entries as usize).min(sizes.len());
+        let partial = sizes.get(whole).map_or(0.0, |size| *size as f64 * entries.fract());
+        Some((sizes[..whole].iter().map(|size| *size as f64).sum::<f64>() + partial) as u64)
+      }
+    }
+  }
+}
+
+fn xing_header(input: &[u8]) -> IResult<&[u8], VbrHeader> {
+  let (input, id) = alt((tag("Xing"), tag("Info")))(input)?;
+  let (input, flags) = be_u32(input)?;
+  let (input, frames) = cond(flags & 0x1 != 0, be_u32)(input)?;
+  let (input, bytes) = cond(flags & 0x2 != 0, be_u32)(input)?;
+  let (input, toc) = cond(flags & 0x4 != 0, take(100usize))(input)?;
+  let (input, quality) = cond(flags & 0x8 != 0, be_u32)(input)?;
+  let kind = if id == b"Xing" { VbrKind::Xing } else { VbrKind::Info };
+  Ok((input, VbrHeader { kind, frames, bytes, toc: toc.map(|toc| Toc::Xing(toc.to_vec())), quality, lame: None }))
+}
+
+fn vbri_header(input: &[u8]) -> IResult<&[u8], VbrHeader> {
+  let (input, _) = tag("VBRI")(input)?;
+  let (input, (_version, _delay, quality)) = tuple((be_u16, be_u16, be_u16))(input)?;
+  let (input, (bytes, frames)) = tuple((be_u32, be_u32))(input)?;
+  let (input, (entries, scale, entry_size, frames_per_entry)) = tuple((be_u16, be_u16, be_u16, be_u16))(input)?;
+  let entry = |input| match entry_size {
+    1 => be_u8(input).map(|(input, size)| (input, size as u32)),
+    2 => be_u16(input).map(|(input, size)| (input, size as u32)),
+    3 => be_u24(input),
+    _ => be_u32(input),
+  };
+  let (input, sizes) = count(entry, entries as usize)(input)?;
+  let sizes = sizes.iter().map(|size| size.checked_mul(scale as u32)).collect::<Option<_>>()
+    .ok_or_else(|| nom::Err::Error(Error::new(input, ErrorKind::Verify)))?;
+  let toc = Some(Toc::Vbri { frames_per_entry, sizes });
+  Ok((input, VbrHeader { kind: VbrKind::Vbri, frames: Some(frames), bytes: Some(bytes), toc, quality: Some(quality as u32), lame: None }))
+}
+
+fn lame_tag(input: &[u8]) -> IResult<&[u8], LameTag> {
+  let (input, encoder) = verify(take(9usize), |encoder: &[u8]| [b"LAME", b"Lavc", b"Lavf"].iter().any(|id| encoder.starts_with(*id)))(input)?;
+  let (input, (method, lowpass, peak, track_gain, album_gain)) = tuple((be_u8, be_u8, be_u32, be_u16, be_u16))(input)?;
+  let (input, (flags, bitrate, delay_padding, _misc, mp3_gain, preset)) = tuple((be_u8, be_u8, be_u24, be_u8, be_i8, be_u16))(input)?;
+  let (input, (music_length, music_crc)) = tuple((be_u32, be_u16))(input)?;
+  Ok((input, LameTag {
+    encoder: String::from_utf8_lossy(encoder).trim_end_matches(['\0', ' ']).to_string(),
+    revision: method >> 4,
+    vbr_method: VbrMethod::from(method & 0x0f),
+    lowpass_hz: lowpass as u32 * 100,
+    peak: (peak != 0).then(|| peak as f32 / (1 << 23) as f32),
+    track_gain: replay_gain(track_gain),
+    album_gain: replay_gain(album_gain),
+    encoding_flags: flags >> 4,
+    ath_type: flags & 0x0f,
+    bitrate,
+    delay: (delay_padding >> 12) as u16,
+    padding: (delay_padding & 0xfff) as u16,
+    mp3_gain,
+    preset: preset & 0x7ff,
+    music_length,
+    music_crc,
+    crc_ok: false,
+  }))
+}
+
+/// Name in the top 3 bits, originator in the next 3, then sign and gain in tenths of dB
+fn replay_gain(field: u16) -> Option<ReplayGain> {
+  let gain = (field & 0x1ff) as f32 / 10.0;
+  match field >> 13 {
+    0 => None,
+    _ => Some(ReplayGain { originator: (field >> 10 & 0b111) as u8, gain_db: if field & 0x200 != 0 { -gain } else { gain } })
+  }
+}
+
+/// CRC-16 with the reversed 0x8005 polynomial, as used by the LAME tag
+fn crc16(data: &[u8]) -> u16 {
+  data.iter().fold(0u16, |crc, byte| {
+    (0..8).fold(crc ^ *byte as u16, |crc, _| if crc & 1 == 1 { crc >> 1 ^ 0xa001 } else { crc >> 1 })
+  })
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::audio_info::{AudioInfo, BitrateMode};
+  use id3rs::mp3_parser::Mp3FrameParser;
+  use id3rs::ID3rs;
+
+  #[test]
+  fn test_info_header() {
+    let tag = ID3rs::read("samples/4tink.mp3").unwrap();
+    let info = tag.audio_info().unwrap();
+    assert!(info.from_header);
+    assert_eq!(info.duration_ms, 600.0);
+    assert_eq!(info.frames, 25);
+    assert_eq!(info.sample_rate, 48000);
+    assert_eq!(info.bitrate_mode, BitrateMode::Constant);
+    assert_eq!(info.average_kbps, 128.0);
+    assert_eq!((info.min_kbps, info.max_kbps), (Some(128), Some(128)));
+  }
+
+  #[test]
+  fn test_junk_before_first_frame() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("junk.mp3");
+    let mut audio = b"\xff\xfb\x00\x00junk".to_vec();
+    audio.extend(fs::read("samples/4tink-raw.mp3").unwrap());
+    fs::write(&path, audio).unwrap();
+    let info = ID3rs::read(&path).unwrap().audio_info().unwrap();
+    assert!(info.from_header);
+    assert_eq!(info.frames, 25);
+  }
+
+  #[test]
+  fn test_header_without_frame_count() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("no-frames.mp3");
+    let mut audio = fs::read("samples/4tink-raw.mp3").unwrap();
+    audio[44..48].copy_from_slice(&[0; 4]);
+    fs::write(&path, audio).unwrap();
+    let info = ID3rs::read(&path).unwrap().audio_info().unwrap();
+    assert!(!info.from_header);
+    assert_eq!(info.frames, 25);
+    assert_eq!(info.average_kbps, 128.0);
+  }
+
+  #[test]
+  fn test_frame_scan() {
+    let info = AudioInfo::scan(Mp3FrameParser::new("samples/4tink-raw.mp3").unwrap()).unwrap();
+    assert!(!info.from_header);
+    assert_eq!(info.frames, 25);
+    assert_eq!(info.duration_ms, 600.0);
+    assert_eq!(info.bitrate_mode, BitrateMode::Constant);
+    assert_eq!(info.average_kbps, 128.0);
+    assert_eq!((info.min_kbps, info.max_kbps), (Some(128), Some(128)));
+  }
+
+  #[test]
+  fn test_update_length() {
+    let mut tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let info = tag.update_length().unwrap();
+    assert_eq!(info.duration_ms.round(), 261460.0);
+    assert_eq!(tag.length(), Some(261460));
+    assert!(tag.dirty);
+  }
+}
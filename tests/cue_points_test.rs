@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::cue_points::{BeatGrid, CuePoint, Cues, GridMarker, Loop, Mp3Timing, Vendor};
+  use id3rs::{log_init, make_rwcopy, ID3rs};
+
+  fn positions(cues: &Cues) -> Vec<(Option<u8>, f64, &str)> {
+    cues.cue_points.iter().map(|cue| (cue.index, cue.position_ms, cue.name.as_str())).collect()
+  }
+
+  #[test]
+  fn test_cues_prefer_serato() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let cues = tag.cues().unwrap();
+    assert_eq!(cues.cue_points.len(), 8);
+    assert_eq!(cues.cue_points[0], CuePoint { index: Some(0), position_ms: 4.0, name: "Cue 1".to_string(), color: Some((0xcc, 0, 0)) });
+    assert!(cues.loops.is_empty());
+  }
+
+  #[test]
+  fn test_cues_from_vendor() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let traktor = tag.cues_from(Vendor::Traktor).unwrap().unwrap();
+    assert!(traktor.cue_points.is_empty());
+    let grid = traktor.beat_grid.unwrap();
+    assert_eq!(grid.markers.len(), 1);
+    assert_eq!(grid.markers[0].bpm.round(), 124.0);
+
+    let mik = tag.cues_from(Vendor::MixedInKey).unwrap().unwrap();
+    assert_eq!(mik.cue_points.len(), 8);
+    assert_eq!(tag.cues_from(Vendor::Rekordbox).unwrap(), None);
+  }
+
+  #[test]
+  fn test_mp3_timing() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let timing = Mp3Timing::read(&tag).unwrap();
+    assert!(timing.lame_tag);
+    assert_eq!(format!("{:.2}", timing.frame_ms), "26.12");
+    assert_eq!(Vendor::Rekordbox.offset_ms(&timing), 0.0);
+
+    let bytes: &[u8] = include_bytes!("../samples/4tink.mp3");
+    let size = bytes[6..10].iter().fold(0usize, |size, b| size << 7 | *b as usize);
+    let mut audio = bytes[10 + size..].to_vec();
+    assert!(Mp3Timing::parse(&audio).lame_tag);
+    let lame = audio.windows(4).position(|w| w == b"LAME").unwrap();
+    audio[lame + 10] ^= 0xff;
+    let timing = Mp3Timing::parse(&audio);
+    assert!(!timing.lame_tag);
+    assert_eq!(timing.frame_ms, 24.0);
+    assert_eq!(Vendor::Rekordbox.offset_ms(&timing), -24.0);
+    assert_eq!(Vendor::Serato.offset_ms(&timing), 0.0);
+  }
+
+  #[test]
+  fn test_cues_without_file() {
+    let bytes = fs::read("samples/psycho.mp3").unwrap();
+    let mut tag = ID3rs::read_from(&mut std::io::Cursor::new(bytes)).unwrap();
+    let cues = tag.cues().unwrap();
+    assert_eq!(cues.cue_points.len(), 8);
+    tag.set_cues(&cues, &[Vendor::Rekordbox]).unwrap();
+    assert!(tag.cues_from(Vendor::Rekordbox).unwrap().is_some());
+  }
+
+  #[test]
+  fn test_convert_serato_cues() {
+    log_init();
+    let rwfile = format!("samples/psycho-cues{}.mp3", rand::random::<u32>());
+    make_rwcopy("samples/psycho.mp3", &rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    let mut cues = tag.cues_from(Vendor::Serato).unwrap().unwrap();
+    cues.cue_points.push(CuePoint::new(None, 1500.0, "Memory"));
+    cues.loops.push(Loop::new(Some(0), 30000.0, 37500.0, "Break"));
+    cues.beat_grid = Some(BeatGrid { markers: vec![GridMarker { position_ms: 52.0, bpm: 124.0 }] });
+    tag.set_cues(&cues, &[Vendor::Traktor, Vendor::Rekordbox, Vendor::MixedInKey, Vendor::Serato]).unwrap();
+    tag.write_to(&rwfile).unwrap();
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    let traktor = tag.cues_from(Vendor::Traktor).unwrap().unwrap();
+    assert_eq!(positions(&traktor), positions(&cues));
+    assert_eq!(traktor.loops[0].end_ms, 37500.0);
+    assert_eq!(traktor.beat_grid.unwrap().markers[0].position_ms, 52.0);
+
+    let rekordbox = tag.cues_from(Vendor::Rekordbox).unwrap().unwrap();
+    assert_eq!(positions(&rekordbox), positions(&cues));
+    let colors = |cues: &Cues| cues.cue_points.iter().map(|cue| cue.color).collect::<Vec<_>>();
+    assert_eq!(colors(&rekordbox), colors(&cues));
+    assert_eq!(rekordbox.loops[0].index, Some(0));
+    assert_eq!(rekordbox.loops[0].name, "Break");
+
+    let mik = tag.cues_from(Vendor::MixedInKey).unwrap().unwrap();
+    assert_eq!(mik.cue_points[8].position_ms, 1500.0);
+
+    let serato = tag.cues_from(Vendor::Serato).unwrap().unwrap();
+    assert_eq!(serato.cue_points.len(), 8, "no memory cues");
+    assert_eq!(serato.loops[0].start_ms, 30000.0);
+    assert_eq!(serato.beat_grid.unwrap().markers[0].bpm, 124.0);
+    fs::remove_file(rwfile).unwrap();
+  }
+}
@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::frame_index::FrameIndex;
+  use id3rs::mp3_parser::{Mp3FrameParser, Resync};
+
+  #[test]
+  fn test_index() {
+    let stream = fs::read("samples/4tink.mp3").unwrap();
+    let mut index = FrameIndex::new(&stream);
+    let entries: Vec<_> = index.by_ref().collect();
+    assert_eq!(entries.len(), 26);
+    assert_eq!((entries[0].offset, entries[0].len), (1124, 384));
+    assert_eq!(entries[25].offset, 1124 + 25 * 384);
+    assert!(entries[0].header.data.is_empty());
+    assert_eq!(index.resyncs(), [Resync { offset: 0, len: 1124 }]);
+
+    let frames: Vec<_> = Mp3FrameParser::new("samples/4tink.mp3").unwrap().map(Result::unwrap).collect();
+    for (entry, frame) in entries.iter().zip(frames) {
+      assert_eq!(entry.data(&stream), frame.data);
+      assert_eq!(entry.to_frame_header(&stream), frame);
+    }
+  }
+
+  #[test]
+  fn test_truncated() {
+    let stream = fs::read("samples/4tink-raw.mp3").unwrap();
+    let mut index = FrameIndex::new(&stream[..stream.len() - 100]);
+    assert_eq!(index.by_ref().count(), 25);
+    assert_eq!(index.resyncs(), [Resync { offset: 25 * 384, len: 284 }]);
+  }
+
+  #[cfg(feature = "mmap")]
+  #[test]
+  fn test_mapped() {
+    let map = unsafe { id3rs::frame_index::map_file("samples/4tink.mp3") }.unwrap();
+    assert_eq!(FrameIndex::new(&map).count(), 26);
+  }
+}
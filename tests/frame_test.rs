@@ -52,7 +52,7 @@ impl Mp3Header<'_> {
     }
   }
 
-  pub fn len(&self) -> u32 {
+  fn len(&self) -> u32 {
     let hz = match self.frequency() {
       0b00 => 44100,
       0b01 => 48000,
@@ -66,7 +66,7 @@ impl Mp3Header<'_> {
 #[cfg(test)]
 mod tests {
   use crate::Mp3Header;
-  use id3rs::mp3_frame::{frame_header, FrameHeader, Layer, Protection, Version};
+  use id3rs::mp3_frame::{frame_header, ChannelMode, Emphasis, FrameHeader, Layer, Protection, Version};
   use std::fs::File;
   use std::io::{Read, Seek, SeekFrom};
 
@@ -83,6 +83,11 @@ mod tests {
       bitrate: 128,
       frequency: 48000,
       padding: 0,
+      private: false,
+      channel_mode: ChannelMode::JointStereo { mid_side: false, intensity: false },
+      copyright: false,
+      original: true,
+      emphasis: Emphasis::None,
       data: vec![],
     });
   }
@@ -128,7 +133,7 @@ mod tests {
     let mut data: Vec<u8> = vec![0; 380];
     sync.append(&mut data);
 
-    let (_, frame) = frame_header(&*sync).ok().unwrap();
+    let (_, frame) = frame_header(&sync).ok().unwrap();
     assert_eq!(frame, FrameHeader {
       version: Version::Version1,
       layer: Layer::Layer3,
@@ -136,8 +141,106 @@ mod tests {
       bitrate: 128,
       frequency: 48000,
       padding: 0,
+      private: false,
+      channel_mode: ChannelMode::JointStereo { mid_side: false, intensity: false },
+      copyright: false,
+      original: true,
+      emphasis: Emphasis::None,
       data: sync,
     });
     assert_eq!(frame.frame_size(), 384)
   }
+
+  fn padded(header: &[u8; 4], size: usize) -> Vec<u8> {
+    let mut frame = Vec::from(header.as_slice());
+    frame.resize(size, 0);
+    frame
+  }
+
+  #[test]
+  fn parse_mpeg2_layer3() {
+    let (_, frame) = frame_header(&padded(b"\xFF\xF3\x80\xC4", 208)).ok().unwrap();
+    assert_eq!(frame.version, Version::Version2);
+    assert_eq!((frame.bitrate, frame.frequency), (64, 22050));
+    assert_eq!(frame.samples_per_frame(), 576);
+    assert_eq!(frame.frame_size(), 208);
+  }
+
+  #[test]
+  fn parse_mpeg25_layer3() {
+    let (_, frame) = frame_header(&padded(b"\xFF\xE3\x84\xC4", 384)).ok().unwrap();
+    assert_eq!(frame.version, Version::Version25);
+    assert_eq!((frame.bitrate, frame.frequency), (64, 12000));
+    assert_eq!(frame.frame_size(), 384);
+    assert_eq!(frame.duration_ms(), 48.0);
+  }
+
+  #[test]
+  fn parse_layer1_and_layer2() {
+    let (_, frame) = frame_header(&padded(b"\xFF\xFF\xC2\xC4", 420)).ok().unwrap();
+    assert_eq!(frame.layer, Layer::Layer1);
+    assert_eq!((frame.bitrate, frame.padding), (384, 1));
+    assert_eq!(frame.samples_per_frame(), 384);
+    assert_eq!(frame.frame_size(), 420);
+
+    let (_, frame) = frame_header(&padded(b"\xFF\xFD\xE4\xC4", 1152)).ok().unwrap();
+    assert_eq!(frame.layer, Layer::Layer2);
+    assert_eq!((frame.bitrate, frame.frequency), (384, 48000));
+    assert_eq!(frame.frame_size(), 1152);
+  }
+
+  #[test]
+  fn reject_invalid_indexes() {
+    assert!(FrameHeader::bitrate_to_kbps(&Version::Version1, &Layer::Layer3, 0).is_none(), "free format");
+    assert!(FrameHeader::bitrate_to_kbps(&Version::Version1, &Layer::Layer3, 15).is_none());
+    assert!(FrameHeader::sampling_to_hz(&Version::Version2, 3).is_none());
+    assert!(FrameHeader::sampling_to_hz(&Version::Reserved, 0).is_none());
+    assert!(frame_header(&padded(b"\xFF\xFB\x04\x44", 384)).is_err());
+    assert!(frame_header(&padded(b"\xFF\xFB\xF4\x44", 384)).is_err());
+    assert!(frame_header(&padded(b"\xFF\xFB\x9C\x44", 384)).is_err());
+    assert!(FrameHeader::frame_sizeof(&Version::Version1, &Layer::Reserved, 128, 48000, 0).is_none());
+    assert!(FrameHeader::frame_sizeof(&Version::Version1, &Layer::Layer3, 128, 0, 0).is_none());
+    assert_eq!(FrameHeader::frame_sizeof(&Version::Version1, &Layer::Layer3, 128, 48000, 0), Some(384));
+    assert!(frame_header(&padded(b"\xFF\xF9\x94\x44", 384)).is_err(), "reserved layer");
+  }
+
+  #[test]
+  fn parse_mode_flags() {
+    let (_, frame) = frame_header(&padded(b"\xFF\xFA\x94\xC9", 384)).ok().unwrap();
+    assert_eq!(frame.crc, Protection::Crc);
+    assert_eq!(frame.channel_mode, ChannelMode::Mono);
+    assert!(frame.copyright && !frame.original && !frame.private);
+    assert_eq!(frame.emphasis, Emphasis::Ms5015);
+
+    let (_, frame) = frame_header(&padded(b"\xFF\xFB\x95\x64", 384)).ok().unwrap();
+    assert_eq!(frame.channel_mode, ChannelMode::JointStereo { mid_side: true, intensity: false });
+    assert!(frame.private);
+  }
+
+  fn protected(header: &[u8; 4], size: usize, crc: u16, data: &[u8]) -> Vec<u8> {
+    let mut frame = padded(header, size);
+    frame[4..6].copy_from_slice(&crc.to_be_bytes());
+    frame[6..6 + data.len()].copy_from_slice(data);
+    frame
+  }
+
+  #[test]
+  fn verify_crc() {
+    let (_, frame) = frame_header(&protected(b"\xFF\xFA\x94\xC9", 384, 0xb5e8, &[])).ok().unwrap();
+    assert_eq!(frame.crc_ok(), Some(true));
+    let (_, frame) = frame_header(&protected(b"\xFF\xFA\x94\xC9", 384, 0xb5e8, &[0x80])).ok().unwrap();
+    assert_eq!(frame.crc_ok(), Some(false));
+
+    let (_, frame) = frame_header(&protected(b"\xFF\xFE\x44\xC0", 128, 0xd466, &[])).ok().unwrap();
+    assert_eq!(frame.layer, Layer::Layer1);
+    assert_eq!(frame.crc_ok(), Some(true));
+
+    // one allocated subband adds its scale factor selection
+    let (_, frame) = frame_header(&protected(b"\xFF\xFC\xA4\x04", 576, 0xef12, &[0x10])).ok().unwrap();
+    assert_eq!(frame.layer, Layer::Layer2);
+    assert_eq!(frame.crc_ok(), Some(true));
+
+    let (_, frame) = frame_header(&padded(b"\xFF\xFB\x94\x44", 384)).ok().unwrap();
+    assert_eq!(frame.crc_ok(), None);
+  }
 }
\ No newline at end of file
@@ -4,12 +4,14 @@ pub const ID3FRAME_SIZE: u32 = 10;
 mod tests {
   use std::convert::TryInto;
   use std::fs;
+  use std::io::Cursor;
 
   use assert_matches::assert_matches;
 
   use crate::ID3FRAME_SIZE;
   use id3rs::id3_parsers::as_syncsafe;
-  use id3rs::{log_init, make_rwcopy, mpck, Frame, ID3rs, GENRE_TAG};
+  use id3rs::validator::validate;
+  use id3rs::{log_init, make_rwcopy, Frame, ID3rs, PaddingPolicy, WriteMode, WriteOptions, GENRE_TAG};
 
   mod v23 {
     use std::str::from_utf8;
@@ -68,7 +70,7 @@ mod tests {
       });
 
 
-      let result = data.map(|data| from_utf8(&data).ok()).flatten()
+      let result = data.and_then(|data| from_utf8(data).ok())
         .map(|str| str.replace('\n', ""));
       assert_eq!(result, Some("eyJhbGdvcml0aG0iOjE0LCJjdWVzIjpbeyJuYW1lIjoiQ3VlIDEiLCJ0aW1lIjo0LjQyNDg1NzU4NTM0MTYxNDZ9LHsibmFtZSI6IkN1ZSAyIiwidGltZSI6MTU1MzguODk5OTc5OTQyNzc3fSx7Im5hbWUiOiJDdWUgMyIsInRpbWUiOjQ2NjA3Ljg1MDIyNDY1NzY0Nn0seyJuYW1lIjoiQ3VlIDQiLCJ0aW1lIjo2MjE0Mi4zMjUzNDcwMTUwODF9LHsibmFtZSI6IkN1ZSA1IiwidGltZSI6MTU1MzQ5LjE3NjA4MTE1ODY5fSx7Im5hbWUiOiJDdWUgNiIsInRpbWUiOjE3MDg4My42NTEyMDM1MTU5NH0seyJuYW1lIjoiQ3VlIDciLCJ0aW1lIjoyMDE5NTIuNjAxNDQ4MjMwNTN9LHsibmFtZSI6IkN1ZSA4IiwidGltZSI6MjE3NDg3LjA3NjU3MDU4Nzc5fV0sInNvdXJjZSI6Im1peGVkaW5rZXkifQ==".to_string()))
     }
@@ -138,7 +140,7 @@ mod tests {
   }
 
   mod v24 {
-    use id3rs::{mpck, Picture, PICTURE_TAG, TITLE_TAG};
+    use id3rs::{Picture, PICTURE_TAG, TITLE_TAG};
 
     use super::*;
 
@@ -158,134 +160,135 @@ mod tests {
     #[test]
     pub fn test_add_padding_raw() {
       rw_test(RAW, |(rofile, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.padding(), 0);
 
-        tag.set_object("HELLO.TXT", "text/plain", "Hello", &"Hello, world".as_bytes());
+        tag.set_object("HELLO.TXT", "text/plain", "Hello", "Hello, world".as_bytes());
         tag.set_extended_text("EnergyLevel", "99");
-        tag.write_to(&rwfile).unwrap();
-        let tag = ID3rs::read(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
+        let tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.padding(), 939);
-        assert_eq!(mpck(&rofile), mpck(&rwfile));
+        assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
       });
     }
 
     #[test]
     pub fn test_add_padding() {
       rw_test(UNPADDED, |(rofile, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.padding(), 0);
 
-        tag.set_object("HELLO.TXT", "text/plain", "Hello", &"Hello, world".as_bytes());
+        tag.set_object("HELLO.TXT", "text/plain", "Hello", "Hello, world".as_bytes());
         tag.set_extended_text("EnergyLevel", "99");
-        tag.write_to(&rwfile).unwrap();
-        let tag = ID3rs::read(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
+        let tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.padding(), 647);
-        assert_eq!(mpck(&rofile), mpck(&rwfile));
+        assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
       });
     }
 
     #[test]
     pub fn test_set_object() {
       rw_test(FILENAME, |(rofile, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.padding(), 831);
 
-        tag.set_object("HELLO.TXT", "text/plain", "Hello", &"Hello, world".as_bytes());
+        tag.set_object("HELLO.TXT", "text/plain", "Hello", "Hello, world".as_bytes());
         tag.set_extended_text("EnergyLevel", "99");
-        tag.write_to(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
 
-        let tag = ID3rs::read(&rwfile).unwrap();
-        assert_eq!(tag.padding(), 747);
-        assert_eq!(mpck(&rofile), mpck(&rwfile));
+        let tag = ID3rs::read(rwfile).unwrap();
+        assert_eq!(tag.padding(), 780);
+        assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
       });
     }
 
     #[test]
     pub fn test_change_extended_text() {
       rw_test(FILENAME, |(rofile, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
-        tag.set_extended_text("OriginalTitle", &tag.title().unwrap().to_string());
+        let mut tag = ID3rs::read(rwfile).unwrap();
+        let title = tag.title().unwrap().to_string();
+        tag.set_extended_text("OriginalTitle", &title);
         tag.set_extended_text("EnergyLevel", "99");
-        tag.write_to(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
 
-        let tag = ID3rs::read(&rwfile).unwrap();
+        let tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.extended_text("OriginalTitle"), Some("Tink"));
         assert_eq!(tag.extended_text("EnergyLevel"), Some("99"));
-        assert_eq!(mpck(&rofile), mpck(&rwfile));
+        assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
       });
     }
 
     #[test]
     pub fn test_clear_popularity() {
       rw_test(FILENAME, |(rofile, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         tag.set_popularity("bas@hoek.com", 3);
         tag.set_track(1, 1);
-        tag.write_to(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
 
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.popularity( "bas@hoek.com"), Some(("bas@hoek.com", 3)));
         assert_eq!(tag.popularities(), vec![("bas@hoek.com", 3)]);
         tag.clear_popularities();
-        tag.write_to(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
 
-        let tag = ID3rs::read(&rwfile).unwrap();
+        let tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.popularities(), vec![]);
-        assert_eq!(mpck(&rofile), mpck(&rwfile));
+        assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
       });
     }
 
     #[test]
     pub fn test_set_popularity() {
       rw_test(FILENAME, |(rofile, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         tag.set_popularity("bas@hoek.com", 3);
         tag.set_track(1, 1);
-        tag.write_to(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
 
-        let tag = ID3rs::read(&rwfile).unwrap();
+        let tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.popularity( "bas@hoek.com"), Some(("bas@hoek.com", 3)));
         assert_eq!(tag.popularities(), vec![("bas@hoek.com", 3)]);
-        assert_eq!(mpck(&rofile), mpck(&rwfile));
+        assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
       });
     }
 
     #[test]
     pub fn test_set_track() {
       rw_test(FILENAME, |(rofile, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         tag.set_track(1, 1);
-        tag.write_to(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
 
-        let tag = ID3rs::read(&rwfile).unwrap();
+        let tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.track(), Some("1/1"));
-        assert_eq!(mpck(&rofile), mpck(&rwfile));
+        assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
       });
     }
 
     #[test]
     pub fn test_set_grouping() {
       rw_test(FILENAME, |(rofile, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         tag.set_grouping("itunes");
-        tag.write_to(&rwfile).unwrap();
+        tag.write_to(rwfile).unwrap();
 
-        let tag = ID3rs::read(&rwfile).unwrap();
+        let tag = ID3rs::read(rwfile).unwrap();
         assert_eq!(tag.grouping(), Some("itunes"));
-        assert_eq!(mpck(&rofile), mpck(&rwfile));
+        assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
       });
     }
 
     #[test]
     pub fn test_attach_picture() {
       rw_test(FILENAME, |(_, _, rwfile)| {
-        let mut tag = ID3rs::read(&rwfile).unwrap();
+        let mut tag = ID3rs::read(rwfile).unwrap();
         let cover = fs::read("samples/cover.jpg").unwrap();
-        tag.set_attached_picture(Picture::FrontCover, "image/jpg", "cover", &*cover);
-        tag.write_to(&rwfile).unwrap();
+        tag.set_attached_picture(Picture::FrontCover, "image/jpg", "cover", &cover);
+        tag.write_to(rwfile).unwrap();
 
-        let tag = ID3rs::read(&rwfile).unwrap();
+        let tag = ID3rs::read(rwfile).unwrap();
         let picture = tag.attached_picture(Picture::FrontCover).unwrap();
         assert_matches!(picture, Frame::Picture { data, .. } => {
           assert_eq!(cover.len(), data.len());
@@ -346,15 +349,15 @@ mod tests {
   #[test]
   pub fn test_changing_genre() {
     rw_test("samples/4tink", |(rofile, _, rwfile)| {
-      let mut tag = ID3rs::read(&rwfile).unwrap();
+      let mut tag = ID3rs::read(rwfile).unwrap();
       assert_eq!(tag.text(GENRE_TAG), Some("sounds"));
       assert_eq!(tag.genre(), Some("sounds"));
       tag.set_genre("notech");
-      tag.write_to(&rwfile).unwrap();
+      tag.write_to(rwfile).unwrap();
 
-      let tag = ID3rs::read(&rwfile).unwrap();
+      let tag = ID3rs::read(rwfile).unwrap();
       assert_eq!(tag.genre(), Some("notech"));
-      assert_eq!(mpck(&rofile), mpck(&rwfile));
+      assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
     });
   }
 
@@ -371,45 +374,215 @@ mod tests {
   #[test]
   pub fn test_change_comment() {
     rw_test("samples/4tink", |(rofile, outfile, _)| {
-      let mut tag = ID3rs::read(&rofile).unwrap();
+      let mut tag = ID3rs::read(rofile).unwrap();
       tag.set_comment("", "New comment");
-      tag.write_to(&outfile).unwrap();
-      assert_eq!(mpck(&rofile), mpck(&outfile));
+      tag.write_to(outfile).unwrap();
+      assert_eq!(validate(rofile).unwrap(), validate(outfile).unwrap());
     });
   }
 
   #[test]
   pub fn test_change_copy() {
     rw_test("samples/4tink", |(rofile, outfile, _)| {
-      let mut tag = ID3rs::read(&rofile).unwrap();
+      let mut tag = ID3rs::read(rofile).unwrap();
       tag.set_title("Bleek");
       tag.set_extended_text("EnergyLevel", "99");
-      tag.write_to(&outfile).unwrap();
-      assert_eq!(mpck(&rofile), mpck(&outfile));
+      tag.write_to(outfile).unwrap();
+      assert_eq!(validate(rofile).unwrap(), validate(outfile).unwrap());
     });
   }
 
   #[test]
   pub fn test_change_inplace() {
     rw_test("samples/4tink", |(rofile, _, rwfile)| {
-      let mut tag = ID3rs::read(&rwfile).unwrap();
+      let mut tag = ID3rs::read(rwfile).unwrap();
       tag.set_title("Bleek");
       tag.set_extended_text("EnergyLevel", "99");
-      tag.write_to(&rwfile).unwrap();
-      assert_eq!(mpck(&rofile), mpck(&rwfile));
+      tag.write_to(rwfile).unwrap();
+      assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
     });
   }
 
+  #[test]
+  pub fn test_change_explicitly_inplace() {
+    rw_test("samples/4tink", |(rofile, _, rwfile)| {
+      let mut tag = ID3rs::read(rwfile).unwrap();
+      tag.set_title("Bleek");
+      tag.write_with(&WriteOptions { mode: WriteMode::InPlace, ..Default::default() }).unwrap();
+      assert!(!tag.dirty);
+      assert_eq!(ID3rs::read(rwfile).unwrap().title(), Some("Bleek"));
+      assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
+    });
+  }
+
+  #[test]
+  #[cfg(unix)]
+  pub fn test_safe_write_keeps_metadata() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, SystemTime};
+
+    let dir = tempfile::tempdir().unwrap();
+    let rwfile = dir.path().join("4tink.mp3");
+    make_rwcopy("samples/4tink.mp3", rwfile.to_str().unwrap()).unwrap();
+    fs::set_permissions(&rwfile, fs::Permissions::from_mode(0o640)).unwrap();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    fs::File::options().write(true).open(&rwfile).unwrap().set_modified(modified).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    tag.set_title("Bleek");
+    tag.set_object("large.bin", "application/octet-stream", "", &vec![0xaa; 4096]);
+    tag.write().unwrap();
+
+    let metadata = fs::metadata(&rwfile).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    assert_eq!(metadata.modified().unwrap(), modified);
+    assert_eq!(ID3rs::read(&rwfile).unwrap().title(), Some("Bleek"));
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1, "no temporary file left");
+  }
+
+  #[test]
+  #[cfg(unix)]
+  pub fn test_overwrite_tag_region() {
+    use std::os::unix::fs::MetadataExt;
+
+    rw_test("samples/4tink", |(rofile, _, rwfile)| {
+      let original = fs::read(rofile).unwrap();
+      let inode = fs::metadata(rwfile).unwrap().ino();
+      let mut tag = ID3rs::read(rwfile).unwrap();
+      let audio = ID3FRAME_SIZE as usize + tag.header_size as usize;
+      tag.set_popularity("bas@hoek.com", 5);
+      tag.write().unwrap();
+      let changed = fs::read(rwfile).unwrap();
+      assert_eq!(fs::metadata(rwfile).unwrap().ino(), inode, "same file");
+      assert_eq!(changed.len(), original.len());
+      assert_eq!(changed[audio..], original[audio..]);
+      assert_eq!(ID3rs::read(rwfile).unwrap().popularity("bas@hoek.com"), Some(("bas@hoek.com", 5)));
+
+      tag.set_popularity("bas@hoek.com", 4);
+      tag.write_with(&WriteOptions { mode: WriteMode::InPlace, ..Default::default() }).unwrap();
+      assert_eq!(fs::metadata(rwfile).unwrap().ino(), inode, "same file");
+      assert_eq!(ID3rs::read(rwfile).unwrap().popularity("bas@hoek.com"), Some(("bas@hoek.com", 4)));
+
+      tag.set_object("large.bin", "application/octet-stream", "", &vec![0xaa; 4096]);
+      tag.write().unwrap();
+      assert_ne!(fs::metadata(rwfile).unwrap().ino(), inode, "renamed over");
+      assert!(tag.header_size > 4096);
+      let changed = fs::read(rwfile).unwrap();
+      assert!(changed.ends_with(&original[audio..]));
+    });
+  }
+
+  #[test]
+  pub fn test_padding_policy() {
+    assert_eq!(PaddingPolicy::Keep.padding(300, 1000), 700);
+    assert_eq!(PaddingPolicy::Keep.padding(1000, 1000), 526);
+    assert_eq!(PaddingPolicy::Fixed(100).padding(300, 1000), 100);
+    assert_eq!(PaddingPolicy::Align(512).padding(300, 1000), 202);
+    assert_eq!(PaddingPolicy::Align(512).padding(502, 1000), 0);
+    assert_eq!(PaddingPolicy::Minimal.padding(300, 1000), 0);
+    assert_eq!(PaddingPolicy::ShrinkOver(500).padding(300, 1000), 500);
+    assert_eq!(PaddingPolicy::ShrinkOver(500).padding(600, 1000), 400);
+  }
+
+  #[test]
+  pub fn test_write_padding_policy() {
+    rw_test("samples/4tink", |(rofile, _, rwfile)| {
+      let mut tag = ID3rs::read(rwfile).unwrap();
+      assert_eq!(tag.padding(), 831);
+      tag.write_with(&WriteOptions { padding: PaddingPolicy::Minimal, ..Default::default() }).unwrap();
+      assert_eq!(ID3rs::read(rwfile).unwrap().padding(), 0);
+      assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
+
+      tag.write_with(&WriteOptions { padding: PaddingPolicy::Align(4096), ..Default::default() }).unwrap();
+      assert_eq!((ID3FRAME_SIZE as u64 + tag.header_size) % 4096, 0);
+      assert_eq!(validate(rofile).unwrap(), validate(rwfile).unwrap());
+    });
+  }
+
+  #[test]
+  pub fn test_unchanged_frames_verbatim() {
+    for sample in ["samples/4tink", "samples/3tink"] {
+      rw_test(sample, |(rofile, outfile, _)| {
+        let tag = ID3rs::read(rofile).unwrap();
+        tag.write_to(outfile).unwrap();
+        assert_eq!(fs::read(rofile).unwrap(), fs::read(outfile).unwrap());
+      });
+    }
+  }
+
+  #[test]
+  pub fn test_change_keeps_version() {
+    rw_test("samples/3tink", |(_, outfile, rwfile)| {
+      let mut tag = ID3rs::read(rwfile).unwrap();
+      assert_eq!(tag.version(), 3);
+      tag.set_title("Bleek");
+      tag.write_to(outfile).unwrap();
+      assert_eq!(&fs::read(outfile).unwrap()[..4], b"ID3\x03");
+      let changed = ID3rs::read(outfile).unwrap();
+      assert_eq!(changed.title(), Some("Bleek"));
+      assert_eq!(changed.version(), 3);
+    });
+  }
+
+  #[test]
+  pub fn test_change_keeps_frame_order() {
+    rw_test("samples/4tink", |(rofile, outfile, _)| {
+      let mut tag = ID3rs::read(rofile).unwrap();
+      let ids = |tag: &ID3rs| tag.frames.iter().filter_map(|frame| match frame {
+        Frame::Text { id, .. } | Frame::Generic { id, .. } | Frame::Comment { id, .. } | Frame::ExtendedText { id, .. }
+        | Frame::Object { id, .. } | Frame::Picture { id, .. } | Frame::Popularity { id, .. } => Some(id.clone()),
+        Frame::Padding { .. } => None,
+      }).collect::<Vec<_>>();
+      let before = ids(&tag);
+      assert_ne!(before.last().map(String::as_str), Some("TIT2"));
+      tag.set_title("Bleek");
+      tag.write_to(outfile).unwrap();
+
+      let changed = ID3rs::read(outfile).unwrap();
+      assert_eq!(ids(&changed), before);
+      assert_eq!(changed.title(), Some("Bleek"));
+      let unchanged = |tag: &ID3rs| tag.frames.iter()
+        .filter(|frame| !matches!(frame, Frame::Text { id, .. } if id == "TIT2") && !matches!(frame, Frame::Padding { .. }))
+        .cloned().collect::<Vec<_>>();
+      assert_eq!(unchanged(&changed), unchanged(&tag));
+    });
+  }
+
+  #[test]
+  pub fn test_change_in_memory() {
+    log_init();
+    let original = fs::read("samples/4tink.mp3").unwrap();
+    let mut tag = ID3rs::read_from(&mut Cursor::new(&original)).unwrap();
+    assert_eq!(tag.path.as_os_str(), "");
+    tag.set_title("Bleek");
+
+    let mut out = Cursor::new(vec![]);
+    tag.write_to_writer(&mut Cursor::new(&original), &mut out).unwrap();
+    let changed = out.into_inner();
+
+    let audio = ID3FRAME_SIZE as usize + tag.header_size as usize;
+    let tag = ID3rs::read_from(&mut Cursor::new(&changed)).unwrap();
+    assert_eq!(tag.title(), Some("Bleek"));
+    assert!(changed.ends_with(&original[audio..]));
+
+    let mut untagged = Cursor::new(vec![]);
+    ID3rs::read_from(&mut Cursor::new(&original[audio..])).unwrap()
+      .write_to_writer(&mut Cursor::new(&original[audio..]), &mut untagged).unwrap();
+    let untagged = untagged.into_inner();
+    assert_eq!(ID3rs::read_from(&mut Cursor::new(&untagged)).unwrap().frames.len(), 1);
+    assert!(untagged.ends_with(&original[audio..]));
+  }
+
   #[test]
   pub fn test_sync_safe() {
     log_init();
 
     assert_eq!(as_syncsafe_bytes(66872), 0x040A38);
-    assert_eq!(as_syncsafe_bytes(0b00001111111_1111111_1111111_1111111u32), 0b01111111011111110111111101111111u32);
+    assert_eq!(as_syncsafe_bytes(0b1111111_1111111_1111111_1111111u32), 0b01111111011111110111111101111111u32);
 
     assert_eq!(as_syncsafe(0b1111111_1111111u32), vec![0, 0, 127, 127]);
     assert_eq!(as_syncsafe(0b1111111_1111111_1111111u32), vec![0, 127, 127, 127]);
-    assert_eq!(as_syncsafe(0b00001111111_1111111_1111111_1111111u32), vec![127, 127, 127, 127]);
+    assert_eq!(as_syncsafe(0b1111111_1111111_1111111_1111111u32), vec![127, 127, 127, 127]);
   }
 
   fn as_syncsafe_bytes(total: u32) -> u32 {
@@ -431,7 +604,7 @@ mod tests {
         Frame::Comment { size, .. } => ID3FRAME_SIZE + size,
         Frame::ExtendedText { size, .. } => ID3FRAME_SIZE + size,
         Frame::Object { size, .. } => ID3FRAME_SIZE + size,
-        Frame::Padding { size } => 0 + size,
+        Frame::Padding { size } => *size,
         Frame::Picture { size, .. } => ID3FRAME_SIZE + size,
         Frame::Popularity { .. } => 0
       });
@@ -445,7 +618,7 @@ mod tests {
         Frame::Comment { size, .. } => ID3FRAME_SIZE + size,
         Frame::ExtendedText { size, .. } => ID3FRAME_SIZE + size,
         Frame::Object { size, .. } => ID3FRAME_SIZE + size,
-        Frame::Padding { size } => 0 + size,
+        Frame::Padding { size } => *size,
         Frame::Picture { size, .. } => ID3FRAME_SIZE + size,
         Frame::Popularity { .. } => 0
       });
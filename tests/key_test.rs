@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::musical_key::{KeyNotation, MusicalKey};
+  use id3rs::{log_init, make_rwcopy, ID3rs};
+
+  #[test]
+  fn test_parse_notations() {
+    assert_eq!(MusicalKey::parse("Am"), Some(MusicalKey::Minor(9)));
+    assert_eq!(MusicalKey::parse("8A"), Some(MusicalKey::Minor(9)));
+    assert_eq!(MusicalKey::parse("1m"), Some(MusicalKey::Minor(9)));
+    assert_eq!(MusicalKey::parse("A minor"), Some(MusicalKey::Minor(9)));
+    assert_eq!(MusicalKey::parse("F#"), Some(MusicalKey::Major(6)));
+    assert_eq!(MusicalKey::parse("Gb"), Some(MusicalKey::Major(6)));
+    assert_eq!(MusicalKey::parse("Ebm"), Some(MusicalKey::Minor(3)));
+    assert_eq!(MusicalKey::parse("12b"), Some(MusicalKey::Major(4)));
+    assert_eq!(MusicalKey::parse("o"), Some(MusicalKey::OffKey));
+    assert_eq!(MusicalKey::parse("13A"), None);
+    assert_eq!(MusicalKey::parse("H"), None);
+  }
+
+  #[test]
+  fn test_parse_mixed_in_key_variants() {
+    assert_eq!(MusicalKey::parse("8A/Am"), Some(MusicalKey::Minor(9)));
+    assert_eq!(MusicalKey::parse("Am/8A"), Some(MusicalKey::Minor(9)));
+    assert_eq!(MusicalKey::parse("8A - 6"), Some(MusicalKey::Minor(9)));
+  }
+
+  #[test]
+  fn test_convert_notations() {
+    let key = MusicalKey::Minor(5);
+    assert_eq!(key.to_notation(KeyNotation::Standard), "Fm");
+    assert_eq!(key.to_notation(KeyNotation::Camelot), "4A");
+    assert_eq!(key.to_notation(KeyNotation::OpenKey), "9m");
+    assert_eq!(MusicalKey::Major(11).to_notation(KeyNotation::Camelot), "1B");
+    assert_eq!(MusicalKey::Major(11).to_notation(KeyNotation::OpenKey), "6d");
+    assert_eq!(MusicalKey::Minor(3).sharps(), "D#m");
+    assert_eq!(MusicalKey::OffKey.to_notation(KeyNotation::Camelot), "o");
+  }
+
+  #[test]
+  fn test_round_trip_wheel() {
+    for number in 1..=12 {
+      for letter in ["A", "B"] {
+        let camelot = format!("{}{}", number, letter);
+        let key = MusicalKey::parse(&camelot).unwrap();
+        assert_eq!(key.to_notation(KeyNotation::Camelot), camelot);
+        assert_eq!(MusicalKey::parse(&key.to_notation(KeyNotation::OpenKey)), Some(key));
+        assert_eq!(MusicalKey::parse(&key.to_notation(KeyNotation::Standard)), Some(key));
+      }
+    }
+  }
+
+  #[test]
+  fn test_musical_key() {
+    log_init();
+    let tag = ID3rs::read("samples/4tink.mp3").unwrap();
+    assert_eq!(tag.musical_key(), Some(MusicalKey::Minor(5)));
+
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    assert_eq!(tag.musical_key(), Some(MusicalKey::Minor(9)));
+  }
+
+  #[test]
+  fn test_normalize_key() {
+    log_init();
+    let rwfile = format!("samples/4tink-key{}.mp3", rand::random::<u32>());
+    make_rwcopy("samples/4tink.mp3", &rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    tag.set_extended_text("initialkey", "4A");
+    assert_eq!(tag.normalize_key(KeyNotation::OpenKey), Some(MusicalKey::Minor(5)));
+    tag.write_to(&rwfile).unwrap();
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    assert_eq!(tag.key(), Some("9m"));
+    assert_eq!(tag.extended_text("initialkey"), Some("9m"));
+    fs::remove_file(rwfile).unwrap();
+  }
+}
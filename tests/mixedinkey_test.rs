@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::mixedinkey::{decode_cue_points, encode_cue_points, encode_energy, encode_key, Cue, MixedInKey, CUE_POINTS_OBJECT, ENERGY_OBJECT, KEY_OBJECT};
+  use id3rs::musical_key::MusicalKey;
+  use id3rs::{log_init, make_rwcopy, Frame, ID3rs};
+
+  fn object_data<'a>(tag: &'a ID3rs, description: &str) -> &'a [u8] {
+    match tag.object_by_description(description) {
+      Some(Frame::Object { data, .. }) => data,
+      _ => panic!("No {} object", description)
+    }
+  }
+
+  #[test]
+  fn test_read_mixed_in_key() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let mik = MixedInKey::read(&tag).unwrap();
+    assert_eq!(mik.energy, Some(6));
+    assert_eq!(mik.key, Some(MusicalKey::Minor(9)));
+    assert_eq!(mik.cues.len(), 8);
+    assert_eq!(mik.cues[0], Cue { name: "Cue 1".to_string(), time_ms: 4.4248575853416146 });
+    assert_eq!(mik.cues[7].time_ms, 217487.0765705878);
+  }
+
+  #[test]
+  fn test_fallback_to_text_frames() {
+    log_init();
+    let tag = ID3rs::read("samples/4tink.mp3").unwrap();
+    let mik = MixedInKey::read(&tag).unwrap();
+    assert_eq!(mik.energy, Some(6));
+    assert_eq!(mik.key, Some(MusicalKey::Minor(5)));
+    assert!(mik.cues.is_empty());
+  }
+
+  #[test]
+  fn test_encode_byte_exact() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let data = object_data(&tag, CUE_POINTS_OBJECT);
+    let cues = decode_cue_points(data).unwrap();
+    assert_eq!(encode_cue_points(&cues).unwrap(), data);
+    assert_eq!(encode_energy(6), object_data(&tag, ENERGY_OBJECT));
+    assert_eq!(encode_key(MusicalKey::Minor(9)), object_data(&tag, KEY_OBJECT));
+  }
+
+  #[test]
+  fn test_encode_non_finite() {
+    for time_ms in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+      assert!(encode_cue_points(&[Cue { name: "Drop".to_string(), time_ms }]).is_err());
+    }
+  }
+
+  #[test]
+  fn test_write_mixed_in_key() {
+    log_init();
+    let rwfile = format!("samples/4tink-mik{}.mp3", rand::random::<u32>());
+    make_rwcopy("samples/4tink.mp3", &rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    let mik = MixedInKey {
+      energy: Some(8),
+      key: Some(MusicalKey::Major(7)),
+      cues: vec![Cue { name: "Drop".to_string(), time_ms: 1234.5 }],
+    };
+    mik.write(&mut tag).unwrap();
+    tag.write_to(&rwfile).unwrap();
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    assert_eq!(tag.extended_text("EnergyLevel"), Some("8"));
+    assert_eq!(tag.key(), Some("G"));
+    assert_eq!(MixedInKey::read(&tag).unwrap(), mik);
+    fs::remove_file(rwfile).unwrap();
+  }
+}
@@ -55,42 +55,42 @@ mod tests {
     let (input, frame) = object_frame(v24_len)(&input).ok().unwrap();
     assert_eq!(frame, Frame::Object { id: OBJECT_TAG.to_string(), size: 80, flags: 0, mime_type: "application/vnd.rekordbox.dat".to_string(), filename: "ANLZ0000.DAT".to_string(), description: "Rekordbox Analysis Data".to_string(), data });
 
-    let (input, frame) = extended_text_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = extended_text_frame(v24_len)(input).ok().unwrap();
     assert_eq!(frame, Frame::ExtendedText { id: EXTENDED_TAG.to_string(), size: 12, flags: 0, description: "Hello".to_string(), value: "World".to_string() });
 
-    let (input, frame) = text_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = text_frame(v24_len)(input).ok().unwrap();
     assert_eq!(frame, Frame::Text { id: TITLE_TAG.to_string(), size: 5, flags: 0, text: "Tink".to_string() });
 
-    let (input, frame) = text_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = text_frame(v24_len)(input).ok().unwrap();
     assert_eq!(frame, Frame::Text { id: ARTIST_TAG.to_string(), size: 6, flags: 0, text: "Apple".to_string() });
 
-    let (input, frame) = comment_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = comment_frame(v24_len)(input).ok().unwrap();
     assert_matches!(frame, Frame::Comment{ id, value, ..} => {
       assert_eq!(id, COMMENT_TAG);
       assert_eq!(value, "From Big Sur");
     });
 
-    let (input, frame) = text_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = text_frame(v24_len)(input).ok().unwrap();
     assert_eq!(frame, Frame::Text { id: GENRE_TAG.to_string(), size: 7, flags: 0, text: "sounds".to_string() });
 
-    let (input, frame) = extended_text_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = extended_text_frame(v24_len)(input).ok().unwrap();
     assert_eq!(frame, Frame::ExtendedText { id: EXTENDED_TAG.to_string(), size: 23, flags: 0, description: "こんにちは".to_string(), value: "世界".to_string() });
 
-    let (input, frame) = text_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = text_frame(v24_len)(input).ok().unwrap();
     assert_eq!(frame, Frame::Text { id: KEY_TAG.to_string(), size: 3, flags: 0, text: "4A".to_string() });
 
-    let (input, frame) = extended_text_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = extended_text_frame(v24_len)(input).ok().unwrap();
     assert_eq!(frame, Frame::ExtendedText { id: EXTENDED_TAG.to_string(), size: 14, flags: 0, description: "EnergyLevel".to_string(), value: "6".to_string() });
 
-    let (input, frame) = text_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = text_frame(v24_len)(input).ok().unwrap();
     assert_eq!(frame, Frame::Text { id: SUBTITLE_TAG.to_string(), size: 1, flags: 0, text: "".to_string() });
 
-    let (input, frame) = generic_frame(v24_len)(&input).ok().unwrap();
+    let (input, frame) = generic_frame(v24_len)(input).ok().unwrap();
     assert_matches!(frame, Frame::Generic{ id, ..} => {
       assert_eq!(id, GROUPING_TAG);
     });
 
-    let (_input, frame) = padding(&input).ok().unwrap();
+    let (_input, frame) = padding(input).ok().unwrap();
     assert_eq!(frame, Frame::Padding { size: 831 });
   }
 
@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use assert_matches::assert_matches;
+  use id3rs::picture::{mime_type, PICTURES};
+  use id3rs::{log_init, make_rwcopy, Frame, ID3rs, Picture};
+
+  #[test]
+  fn test_picture_types() {
+    for (index, picture) in PICTURES.iter().enumerate() {
+      assert_eq!(Picture::try_from(index as u8), Ok(*picture));
+      assert_eq!(u8::from(*picture), index as u8);
+    }
+    assert_eq!(Picture::FrontCover as u8, 3);
+    assert_eq!(Picture::PublisherLogo as u8, 0x14);
+    assert_eq!(Picture::try_from(0x14), Ok(Picture::PublisherLogo));
+    assert_eq!(Picture::try_from(0x15), Err(0x15));
+  }
+
+  #[test]
+  fn test_mime_type() {
+    assert_eq!(mime_type(&fs::read("samples/cover.jpg").unwrap()), Some("image/jpeg"));
+    assert_eq!(mime_type(&fs::read("samples/bzhoek.png").unwrap()), Some("image/png"));
+    assert_eq!(mime_type(b"GIF89a\x01\x00"), Some("image/gif"));
+    assert_eq!(mime_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "), Some("image/webp"));
+    assert_eq!(mime_type(b"Hello, world"), None);
+  }
+
+  #[test]
+  fn test_pictures() {
+    log_init();
+    let tag = ID3rs::read("samples/3tank.mp3").unwrap();
+    let pictures = tag.pictures();
+    assert_eq!(pictures.len(), 1);
+    assert_matches!(pictures[0], Frame::Picture { kind: 3, .. });
+  }
+
+  #[test]
+  fn test_pictures_by_description() {
+    log_init();
+    let rwfile = format!("samples/3tank-pictures{}.mp3", rand::random::<u32>());
+    make_rwcopy("samples/3tank.mp3", &rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    let cover = fs::read("samples/cover.jpg").unwrap();
+    tag.set_picture(Picture::FrontCover, "alternate", &cover).unwrap();
+    tag.set_picture(Picture::Artist, "", &cover).unwrap();
+    assert!(tag.set_picture(Picture::Leaflet, "", b"Hello, world").is_err());
+    tag.write_to(&rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    assert_eq!(tag.pictures().len(), 3);
+    assert_matches!(tag.attached_picture_by_description(Picture::FrontCover, "alternate"), Some(Frame::Picture { mime_type, data, .. }) => {
+      assert_eq!(mime_type, "image/jpeg");
+      assert_eq!(data, &cover);
+    });
+    assert_matches!(tag.attached_picture(Picture::FrontCover), Some(Frame::Picture { mime_type, .. }) => {
+      assert_eq!(mime_type, "image/png");
+    });
+
+    assert!(tag.remove_attached_picture(Picture::FrontCover, "alternate"));
+    assert!(!tag.remove_attached_picture(Picture::FrontCover, "alternate"));
+    assert_eq!(tag.remove_attached_pictures(Picture::Artist), 1);
+    assert_eq!(tag.pictures().len(), 1);
+    fs::remove_file(rwfile).unwrap();
+  }
+}
+
+#[cfg(all(test, feature = "image"))]
+mod image_tests {
+  use std::fs;
+
+  use assert_matches::assert_matches;
+  use id3rs::picture::{image_info, ImageFormat};
+  use id3rs::{make_rwcopy, Frame, ID3rs, Picture};
+
+  #[test]
+  fn test_image_info() {
+    let info = image_info(&fs::read("samples/bzhoek.png").unwrap()).unwrap();
+    assert_eq!(info.format, ImageFormat::Png);
+    assert_eq!((info.width, info.height, info.bits_per_pixel), (300, 297, 32));
+    let info = image_info(&fs::read("samples/cover.jpg").unwrap()).unwrap();
+    assert_eq!(info.format, ImageFormat::Jpeg);
+    assert_eq!((info.width, info.height, info.bits_per_pixel), (500, 500, 24));
+    assert!(image_info(b"Hello, world").is_err());
+  }
+
+  #[test]
+  fn test_normalize_cover() {
+    let rwfile = format!("samples/3tank-pictures{}.mp3", rand::random::<u32>());
+    make_rwcopy("samples/3tank.mp3", &rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    assert!(tag.normalize_cover(200, ImageFormat::Jpeg, 80).unwrap());
+    assert!(!tag.normalize_cover(200, ImageFormat::Jpeg, 80).unwrap());
+    tag.write_to(&rwfile).unwrap();
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    assert_matches!(tag.attached_picture(Picture::FrontCover), Some(Frame::Picture { mime_type, data, .. }) => {
+      assert_eq!(mime_type, "image/jpeg");
+      let info = image_info(data).unwrap();
+      assert_eq!((info.format, info.width, info.height), (ImageFormat::Jpeg, 200, 198));
+    });
+    fs::remove_file(rwfile).unwrap();
+  }
+}
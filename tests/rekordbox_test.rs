@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::rekordbox::{Anlz, Beat, CueKind, CueListKind, DAT_FILENAME, EXT_FILENAME};
+  use id3rs::{log_init, make_rwcopy, ID3rs};
+
+  // 5eep.mp3 has an unsupported tag version, so take the embedded analysis file from the raw bytes
+  fn embedded_anlz() -> &'static [u8] {
+    let bytes: &[u8] = include_bytes!("../samples/5eep.mp3");
+    let start = bytes.windows(4).position(|w| w == b"PMAI").unwrap();
+    let len = u32::from_be_bytes(bytes[start + 8..start + 12].try_into().unwrap()) as usize;
+    &bytes[start..start + len]
+  }
+
+  #[test]
+  fn test_parse_anlz() {
+    let anlz = Anlz::parse(embedded_anlz()).unwrap();
+    let tags: Vec<&str> = anlz.sections.iter().map(|s| s.tag.as_str()).collect();
+    assert_eq!(tags, ["PPTH", "PVBR", "PQTZ", "PWAV", "PWV2", "PCOB", "PCOB"]);
+    assert!(anlz.path().unwrap().starts_with("/Contents/Flug, Sebastián López/"));
+    assert_eq!(anlz.to_bytes(), embedded_anlz());
+  }
+
+  #[test]
+  fn test_beat_grid() {
+    let anlz = Anlz::parse(embedded_anlz()).unwrap();
+    let beats = anlz.beat_grid().unwrap();
+    assert_eq!(beats[0], Beat { beat_number: 1, tempo: 13333, time_ms: 58 });
+    assert_eq!(beats[1].beat_number, 2);
+    assert_eq!(beats[0].bpm(), 133.33);
+  }
+
+  #[test]
+  fn test_cues() {
+    let anlz = Anlz::parse(embedded_anlz()).unwrap();
+    let lists = anlz.cue_lists().unwrap();
+    assert_eq!(lists.len(), 2);
+    assert_eq!(lists[0].kind, CueListKind::HotCue);
+    assert!(lists[0].cues.is_empty());
+    assert_eq!(lists[1].kind, CueListKind::Memory);
+    assert_eq!(lists[1].cues.len(), 7);
+
+    let cue = &lists[1].cues[0];
+    assert_eq!(cue.hot_cue, 0);
+    assert_eq!(cue.kind, CueKind::Cue);
+    assert_eq!(cue.time_ms, 62);
+    assert_eq!(cue.loop_end_ms, None);
+  }
+
+  #[test]
+  fn test_set_cue_lists() {
+    let mut anlz = Anlz::parse(embedded_anlz()).unwrap();
+    let lists = anlz.cue_lists().unwrap();
+    anlz.set_cue_lists(&lists);
+    assert_eq!(anlz.to_bytes(), embedded_anlz());
+
+    let mut anlz = Anlz::default();
+    anlz.set_cue_lists(&lists[1..]);
+    let anlz = Anlz::parse(&anlz.to_bytes()).unwrap();
+    assert_eq!(anlz.cue_lists().unwrap(), &lists[1..]);
+  }
+
+  #[test]
+  fn test_set_extended_cue_lists() {
+    let mut lists = Anlz::parse(embedded_anlz()).unwrap().cue_lists().unwrap();
+    lists[1].cues[0].comment = "Drop".to_string();
+    lists[1].cues[0].color_id = 3;
+    lists[1].cues[1].color = Some((40, 226, 20));
+    let mut anlz = Anlz::default();
+    anlz.set_cue_lists(&lists);
+    let tags: Vec<&str> = anlz.sections.iter().map(|s| s.tag.as_str()).collect();
+    assert_eq!(tags, ["PCOB", "PCOB", "PCO2", "PCO2"]);
+    let anlz = Anlz::parse(&anlz.to_bytes()).unwrap();
+    assert_eq!(anlz.cue_lists().unwrap(), lists);
+  }
+
+  #[test]
+  fn test_waveforms() {
+    let anlz = Anlz::parse(embedded_anlz()).unwrap();
+    let preview = anlz.preview().unwrap();
+    assert_eq!(preview.len(), 400);
+    assert_eq!(preview[0].height, 0x57 & 0x1f);
+    assert_eq!(preview[0].whiteness, 2);
+    assert_eq!(anlz.tiny_preview().unwrap().len(), 100);
+    assert_eq!(anlz.detail(), None);
+    assert_eq!(anlz.color_detail(), None);
+  }
+
+  #[test]
+  fn test_open_standalone() {
+    let file = format!("samples/ANLZ-rw{}.DAT", rand::random::<u32>());
+    fs::write(&file, embedded_anlz()).unwrap();
+    let anlz = Anlz::open(&file).unwrap();
+    assert_eq!(anlz.beat_grid().unwrap().len(), anlz.section("PQTZ").unwrap().data.len() / 8);
+    fs::remove_file(file).unwrap();
+
+    assert!(Anlz::parse(b"PMAI\x00\x00\x00\x1c").is_err());
+  }
+
+  #[test]
+  fn test_embed_anlz() {
+    log_init();
+    let rwfile = format!("samples/4tink-anlz{}.mp3", rand::random::<u32>());
+    make_rwcopy("samples/4tink.mp3", &rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    assert!(Anlz::read(&tag, DAT_FILENAME).is_err(), "placeholder payload");
+    Anlz::parse(embedded_anlz()).unwrap().write(&mut tag, DAT_FILENAME);
+    tag.write_to(&rwfile).unwrap();
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    let anlz = Anlz::read(&tag, DAT_FILENAME).unwrap().unwrap();
+    assert_eq!(anlz.to_bytes(), embedded_anlz());
+    assert_eq!(Anlz::read(&tag, EXT_FILENAME).unwrap(), None);
+    fs::remove_file(rwfile).unwrap();
+  }
+}
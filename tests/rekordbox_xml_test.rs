@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+  use id3rs::cue_points::Vendor;
+  use id3rs::musical_key::MusicalKey;
+  use id3rs::rekordbox_xml::{DjPlaylists, RATING_AUTHOR};
+  use id3rs::{log_init, make_rwcopy, ID3rs};
+
+  const COLLECTION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<DJ_PLAYLISTS Version="1.0.0">
+  <PRODUCT Name="rekordbox" Version="6.8.5" Company="AlphaTheta"/>
+  <COLLECTION Entries="1">
+    <TRACK TrackID="42" Name="Tink Tonk" Artist="Apple" Composer="" Album="Big Sur" Grouping=""
+           Genre="Sounds" Kind="MP3 File" Size="10467" TotalTime="1" DiscNumber="0" TrackNumber="0"
+           Year="0" AverageBpm="128.00" DateAdded="2024-01-01" BitRate="128" SampleRate="48000"
+           Comments="" PlayCount="0" Rating="204" Location="LOCATION" Remixer=""
+           Tonality="Fm" Label="" Mix="">
+      <TEMPO Inizio="0.050" Bpm="128.00" Metro="4/4" Battito="1"/>
+      <POSITION_MARK Name="Intro" Type="0" Start="0.100" Num="0" Red="40" Green="226" Blue="20"/>
+      <POSITION_MARK Name="" Type="0" Start="0.250" Num="-1"/>
+      <POSITION_MARK Name="Roll" Type="4" Start="0.300" End="0.400" Num="1"/>
+    </TRACK>
+  </COLLECTION>
+  <PLAYLISTS>
+    <NODE Type="0" Name="ROOT" Count="1">
+      <NODE Name="Tinks" Type="1" KeyType="0" Entries="1">
+        <TRACK Key="42"/>
+      </NODE>
+    </NODE>
+  </PLAYLISTS>
+</DJ_PLAYLISTS>
+"#;
+
+  #[test]
+  fn test_parse_collection() {
+    let playlists = DjPlaylists::parse(&COLLECTION.replace("LOCATION", "file://localhost/Music/Tink%20Tonk.mp3")).unwrap();
+    assert_eq!(playlists.product.name, "rekordbox");
+    let track = &playlists.collection.tracks[0];
+    assert_eq!(track.path().unwrap().to_str(), Some("/Music/Tink Tonk.mp3"));
+    assert_eq!(track.average_bpm, Some(128.0));
+    assert_eq!(track.musical_key(), Some(MusicalKey::Minor(5)));
+    assert_eq!(track.position_marks[2].end, Some(0.4));
+    let root = &playlists.playlists.as_ref().unwrap().nodes[0];
+    assert_eq!(root.nodes[0].tracks[0].key, "42");
+  }
+
+  #[test]
+  fn test_import_track() {
+    log_init();
+    let dir = tempfile::tempdir().unwrap();
+    let rwfile = dir.path().join("tink tonk.mp3");
+    make_rwcopy("samples/4tink.mp3", rwfile.to_str().unwrap()).unwrap();
+    let location = format!("file://localhost{}", rwfile.canonicalize().unwrap().to_str().unwrap().replace(' ', "%20"));
+    let playlists = DjPlaylists::parse(&COLLECTION.replace("LOCATION", &location)).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    assert!(playlists.import(&mut tag, &[Vendor::Serato]).unwrap());
+    tag.write_to(&rwfile).unwrap();
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    assert_eq!(tag.title(), Some("Tink Tonk"));
+    assert_eq!(tag.album(), Some("Big Sur"));
+    assert_eq!(tag.popularity(RATING_AUTHOR), Some((RATING_AUTHOR, 4)));
+    assert_eq!(tag.bpm(), Some(128.0));
+    assert_eq!(tag.key(), Some("Fm"));
+
+    let cues = tag.cues_from(Vendor::Serato).unwrap().unwrap();
+    assert_eq!(cues.cue_points.len(), 1, "memory cues are not Serato hot cues");
+    assert_eq!(cues.cue_points[0].color, Some((40, 226, 20)));
+    assert_eq!(cues.loops[0].index, Some(1));
+    assert_eq!(cues.beat_grid.unwrap().markers[0].bpm, 128.0);
+
+    let other = ID3rs::read("samples/tink.mp3").unwrap();
+    assert_eq!(playlists.track(&other.path), None);
+  }
+
+  #[test]
+  fn test_export_directory() {
+    log_init();
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["psycho.mp3", "4tink.mp3", "5eep.mp3"] {
+      make_rwcopy(&format!("samples/{}", name), dir.path().join(name).to_str().unwrap()).unwrap();
+    }
+    let playlists = DjPlaylists::from_directory(dir.path()).unwrap();
+    assert_eq!(playlists.collection.entries, 2);
+
+    let psycho = playlists.track(dir.path().join("psycho.mp3")).unwrap();
+    assert_eq!(psycho.tonality.as_deref(), Some("Am"));
+    assert_eq!(psycho.position_marks.len(), 8);
+    assert_eq!(psycho.position_marks[0].start, 0.004);
+    assert!(playlists.track(dir.path().join("5eep.mp3")).is_none(), "unsupported tag version");
+
+    let xml = playlists.to_xml().unwrap();
+    assert!(xml.starts_with("<?xml"));
+    assert_eq!(DjPlaylists::parse(&xml).unwrap(), playlists);
+  }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::serato::{Analysis, Autotags, BeatGrid, BeatGridMarker, Cue, Loop, Markers2, Markers2Entry, Overview, Rgb, Serato, MARKERS2_OBJECT};
+  use id3rs::{log_init, make_rwcopy, Frame, ID3rs};
+
+  fn markers2_data(tag: &ID3rs) -> &[u8] {
+    match tag.object_by_description(MARKERS2_OBJECT) {
+      Some(Frame::Object { data, .. }) => data,
+      _ => panic!("No markers")
+    }
+  }
+
+  #[test]
+  fn test_read_markers2() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let serato = Serato::read(&tag).unwrap();
+    let markers = serato.markers.unwrap();
+    let cues = markers.cues();
+    assert_eq!(cues.len(), 8);
+    assert_eq!(cues[0], &Cue { index: 0, position_ms: 4, color: Rgb(0xcc, 0, 0), name: "Cue 1".to_string() });
+    assert_eq!(cues[7].position_ms, 217487);
+    assert!(markers.loops().is_empty());
+    assert_eq!(serato.beatgrid, None);
+  }
+
+  #[test]
+  fn test_markers2_byte_exact() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let data = markers2_data(&tag);
+    assert_eq!(Markers2::parse(data).unwrap().to_bytes(), data);
+  }
+
+  #[test]
+  fn test_markers2_serato_layout() {
+    let mut markers = Markers2 { entries: vec![Markers2Entry::Color(Rgb(0xff, 0xff, 0xff)), Markers2Entry::BpmLock(false)], ..Default::default() };
+    markers.set_cues(
+      vec![Cue { index: 0, position_ms: 1000, color: Rgb(0xcc, 0, 0), name: "".to_string() }],
+      vec![Loop { index: 0, start_ms: 2000, end_ms: 4000, color: Rgb(0x27, 0xaa, 0xe1), locked: false, name: "Loop".to_string() }]);
+    let data = markers.to_bytes();
+    assert_eq!(data.len(), 470);
+    assert_eq!(&data[..2], b"\x01\x01");
+    let parsed = Markers2::parse(&data).unwrap();
+    assert_eq!(parsed, markers);
+    assert_eq!(parsed.color(), Some(Rgb(0xff, 0xff, 0xff)));
+    assert_eq!(parsed.loops()[0].end_ms, 4000);
+  }
+
+  #[test]
+  fn test_beatgrid() {
+    let data = b"\x01\x00\x00\x00\x00\x02\x3f\x00\x00\x00\x00\x00\x00\x10\x41\x20\x00\x00\x42\xf6\x00\x00\x00";
+    let grid = BeatGrid::parse(data).unwrap();
+    assert_eq!(grid.markers, vec![
+      BeatGridMarker::NonTerminal { position: 0.5, beats_till_next: 16 },
+      BeatGridMarker::Terminal { position: 10.0, bpm: 123.0 },
+    ]);
+    assert_eq!(grid.bpm(), Some(123.0));
+    assert_eq!(grid.to_bytes(), data);
+  }
+
+  #[test]
+  fn test_autotags_overview_analysis() {
+    let data = b"\x01\x01115.00\x00-3.257\x000.000\x00";
+    let autotags = Autotags::parse(data).unwrap();
+    assert_eq!(autotags, Autotags { bpm: 115.0, auto_gain: -3.257, gain_db: 0.0 });
+    assert_eq!(autotags.to_bytes(), data);
+
+    let mut data = vec![1u8, 5];
+    data.extend((0..32u8).collect::<Vec<_>>());
+    let overview = Overview::parse(&data).unwrap();
+    assert_eq!(overview.blocks.len(), 2);
+    assert_eq!(overview.to_bytes(), data);
+    assert!(Overview::parse(&data[..20]).is_err());
+
+    assert_eq!(Analysis::parse(b"\x02\x01").unwrap(), Analysis { major: 2, minor: 1 });
+  }
+
+  #[test]
+  fn test_write_serato() {
+    log_init();
+    let rwfile = format!("samples/psycho-serato{}.mp3", rand::random::<u32>());
+    make_rwcopy("samples/psycho.mp3", &rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    let mut serato = Serato::read(&tag).unwrap();
+    let markers = serato.markers.as_mut().unwrap();
+    let mut cues: Vec<Cue> = markers.cues().into_iter().cloned().collect();
+    cues[0].name = "Intro".to_string();
+    markers.set_cues(cues, vec![]);
+    serato.autotags = Some(Autotags { bpm: 124.0, auto_gain: -1.5, gain_db: 0.0 });
+    serato.write(&mut tag);
+    tag.write_to(&rwfile).unwrap();
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    let serato = Serato::read(&tag).unwrap();
+    assert_eq!(serato.markers.unwrap().cues()[0].name, "Intro");
+    assert_eq!(serato.autotags.unwrap().bpm, 124.0);
+    fs::remove_file(rwfile).unwrap();
+  }
+}
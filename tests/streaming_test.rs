@@ -8,17 +8,19 @@ fn init() {
 
 #[cfg(test)]
 mod tests {
-  use id3rs::mp3_frame::FrameHeader;
-  use id3rs::mp3_parser::Mp3FrameParser;
+  use id3rs::mp3_frame::{ChannelMode, Emphasis, FrameHeader};
+  use id3rs::mp3_parser::{Mp3FrameParser, Resync};
+  use std::fs;
   use std::fs::File;
-  use std::io::Write;
+  use std::io;
+  use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 
   #[test]
   fn test_writing() {
     let mut file = File::create("frames.mp3").unwrap();
     let file_iter = Mp3FrameParser::new("samples/4tink.mp3").unwrap();
     for header in file_iter {
-      file.write_all(&*header.data).unwrap();
+      file.write_all(&header.unwrap().data).unwrap();
     }
     file.flush().unwrap();
   }
@@ -30,6 +32,42 @@ mod tests {
     assert_eq!(26, frames.len());
   }
 
+  #[test]
+  fn test_resyncs() {
+    let mut audio = fs::read("samples/4tink-raw.mp3").unwrap();
+    audio.splice(384..384, *b"junk");
+    audio.extend(b"\xFF\xFB\x94");
+    let mut parser = Mp3FrameParser::from_reader(Cursor::new(audio));
+    assert_eq!(parser.by_ref().map(Result::unwrap).count(), 26);
+    assert_eq!(parser.resyncs(), [Resync { offset: 384, len: 4 }, Resync { offset: 9988, len: 3 }]);
+    assert_eq!(parser.offset(), 9991);
+  }
+
+  #[test]
+  fn test_seekable_offsets() {
+    let mut file = File::open("samples/4tink.mp3").unwrap();
+    file.seek(SeekFrom::Start(1000)).unwrap();
+    let mut parser = Mp3FrameParser::from_seekable(file).unwrap();
+    parser.next().unwrap().unwrap();
+    assert_eq!(parser.resyncs(), [Resync { offset: 1000, len: 124 }]);
+    assert_eq!(parser.offset(), 1124 + 384);
+  }
+
+  struct Failing;
+
+  impl Read for Failing {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+      Err(io::Error::other("unplugged"))
+    }
+  }
+
+  #[test]
+  fn test_read_error() {
+    let mut parser = Mp3FrameParser::from_reader(Failing);
+    assert_eq!(parser.next().unwrap().unwrap_err().to_string(), "unplugged");
+    assert!(parser.next().is_none());
+  }
+
   #[test]
   fn test_layer3_size() {
     let header = FrameHeader {
@@ -39,6 +77,11 @@ mod tests {
       bitrate: 128,
       frequency: 44100,
       padding: 0,
+      private: false,
+      channel_mode: ChannelMode::JointStereo { mid_side: false, intensity: false },
+      copyright: false,
+      original: true,
+      emphasis: Emphasis::None,
       data: vec![],
     };
     let size = header.frame_size();
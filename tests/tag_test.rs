@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+  use assert_matches::assert_matches;
+  use id3rs::{Frame, Picture, Tag};
+
+  fn tag() -> Tag {
+    let mut tag = Tag::new();
+    tag.set_title("Tink");
+    tag.set_artist("Apple");
+    tag.set_comment("", "こんにちは");
+    tag.set_extended_text("EnergyLevel", "6");
+    tag.set_popularity("bas@hoek.com", 4);
+    tag.set_object("ANLZ0000.DAT", "application/octet-stream", "Data", b"Hello, world");
+    tag.set_attached_picture(Picture::FrontCover, "image/png", "世界", b"\x89PNG");
+    tag
+  }
+
+  fn assert_frames(tag: &Tag) {
+    assert_eq!(tag.title(), Some("Tink"));
+    assert_eq!(tag.artist(), Some("Apple"));
+    assert_eq!(tag.comment(), Some("こんにちは"));
+    assert_eq!(tag.extended_text("EnergyLevel"), Some("6"));
+    assert_eq!(tag.popularity("bas@hoek.com"), Some(("bas@hoek.com", 4)));
+    assert_matches!(tag.object_by_filename("ANLZ0000.DAT"), Some(Frame::Object { description, data, .. }) => {
+      assert_eq!(description, "Data");
+      assert_eq!(data, b"Hello, world");
+    });
+    assert_matches!(tag.attached_picture_by_description(Picture::FrontCover, "世界"), Some(Frame::Picture { data, .. }) => {
+      assert_eq!(data, b"\x89PNG");
+    });
+  }
+
+  #[test]
+  fn test_empty() {
+    let tag = Tag::new();
+    assert!(tag.frames.is_empty());
+    assert!(!tag.dirty);
+    assert_eq!(tag.to_bytes(4, 0).unwrap(), b"ID3\x04\x00\x00\x00\x00\x00\x00");
+  }
+
+  #[test]
+  fn test_v24_round_trip() {
+    let tag = tag();
+    assert!(tag.dirty);
+    let bytes = tag.to_bytes(4, 100).unwrap();
+    assert_eq!(&bytes[..4], b"ID3\x04");
+    assert!(bytes.ends_with(&[0; 100]));
+
+    let parsed = Tag::parse(&bytes).unwrap();
+    assert!(!parsed.dirty);
+    assert_frames(&parsed);
+    assert_eq!(parsed.padding(), 100);
+  }
+
+  #[test]
+  fn test_v23_round_trip() {
+    let bytes = tag().to_bytes(3, 0).unwrap();
+    assert_eq!(&bytes[..4], b"ID3\x03");
+    assert_frames(&Tag::parse(&bytes).unwrap());
+  }
+
+  #[test]
+  fn test_invalid() {
+    assert_eq!(tag().to_bytes(2, 0).err().unwrap().to_string(), "Invalid version: 2");
+    assert!(Tag::parse(b"TAG").is_err());
+    assert!(Tag::parse(b"ID3\x04\x00\x00\x00\x00\x01\x00").is_err());
+  }
+}
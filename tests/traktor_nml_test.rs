@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use id3rs::cue_points::Vendor;
+  use id3rs::musical_key::MusicalKey;
+  use id3rs::traktor::CueKind;
+  use id3rs::traktor_nml::{Field, Nml, SyncMode, RATING_AUTHOR};
+  use id3rs::{log_init, make_rwcopy, ID3rs};
+
+  const COLLECTION: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no" ?>
+<NML VERSION="19"><HEAD COMPANY="www.native-instruments.com" PROGRAM="Traktor"></HEAD>
+<MUSICFOLDERS></MUSICFOLDERS>
+<COLLECTION ENTRIES="1"><ENTRY MODIFIED_DATE="2024/1/1" MODIFIED_TIME="3600" TITLE="Tonk"><LOCATION DIR="{dir}" FILE="4tink.mp3" VOLUME="Macintosh HD" VOLUMEID="Macintosh HD"></LOCATION>
+<ALBUM TITLE="Big Sur"></ALBUM>
+<MODIFICATION_INFO AUTHOR_TYPE="user"></MODIFICATION_INFO>
+<INFO BITRATE="128000" GENRE="sounds" KEY="4A" RANKING="204" FLAGS="12" FILESIZE="10"></INFO>
+<TEMPO BPM="128.000000" BPM_QUALITY="100.000000"></TEMPO>
+<MUSICAL_KEY VALUE="17"></MUSICAL_KEY>
+<CUE_V2 NAME="AutoGrid" DISPL_ORDER="0" TYPE="4" START="52.000000" LEN="0.000000" REPEATS="-1" HOTCUE="-1"></CUE_V2>
+<CUE_V2 NAME="Drop &amp; Roll" DISPL_ORDER="0" TYPE="0" START="100.000000" LEN="0.000000" REPEATS="-1" HOTCUE="0"></CUE_V2>
+</ENTRY>
+</COLLECTION>
+<SETS ENTRIES="0"></SETS>
+<PLAYLISTS><NODE TYPE="FOLDER" NAME="$ROOT"><SUBNODES COUNT="0"/></NODE>
+</PLAYLISTS>
+</NML>
+"#;
+
+  fn collection(dir: &Path) -> String {
+    let dir = dir.canonicalize().unwrap().to_str().unwrap().replace('/', "/:");
+    COLLECTION.replace("{dir}", &format!("{}/:", dir))
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let nml = Nml::parse(COLLECTION).unwrap();
+    assert_eq!(nml.to_xml(), COLLECTION);
+  }
+
+  #[test]
+  fn test_entries() {
+    let nml = Nml::parse(&COLLECTION.replace("{dir}", "/:Users/:bas/:Music/:")).unwrap();
+    let entries = nml.entries();
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.path.as_deref(), Some(Path::new("/Users/bas/Music/4tink.mp3")));
+    assert_eq!(entry.title.as_deref(), Some("Tonk"));
+    assert_eq!(entry.artist, None);
+    assert_eq!(entry.rating, Some(4));
+    assert_eq!(entry.bpm, Some(128.0));
+    assert_eq!(entry.key, Some(MusicalKey::Minor(5)));
+    let cues = entry.cues.as_ref().unwrap();
+    assert_eq!(cues[1].name, "Drop & Roll");
+    assert_eq!(cues[1].kind, CueKind::Cue);
+
+    let neutral = entry.neutral_cues();
+    assert_eq!(neutral.cue_points[0].index, Some(0));
+    assert_eq!(neutral.beat_grid.unwrap().markers[0].bpm, 128.0);
+  }
+
+  #[test]
+  fn test_sync_two_way() {
+    log_init();
+    let dir = tempfile::tempdir().unwrap();
+    let rwfile = dir.path().join("4tink.mp3");
+    make_rwcopy("samples/4tink.mp3", rwfile.to_str().unwrap()).unwrap();
+    let mut nml = Nml::parse(&collection(dir.path())).unwrap();
+
+    let report = nml.sync(SyncMode::TwoWay, &[Vendor::Serato]).unwrap();
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].field, Field::Title);
+    assert_eq!(report.conflicts[0].collection, r#""Tonk""#);
+    let to_tags: Vec<Field> = report.to_tags.iter().map(|(_, field)| *field).collect();
+    assert_eq!(to_tags, [Field::Album, Field::Rating, Field::Bpm, Field::Cues]);
+    let to_collection: Vec<Field> = report.to_collection.iter().map(|(_, field)| *field).collect();
+    assert_eq!(to_collection, [Field::Artist]);
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    assert_eq!(tag.title(), Some("Tink"));
+    assert_eq!(tag.album(), Some("Big Sur"));
+    assert_eq!(tag.popularity(RATING_AUTHOR), Some((RATING_AUTHOR, 4)));
+    assert_eq!(tag.bpm(), Some(128.0));
+    let cues = tag.cues_from(Vendor::Serato).unwrap().unwrap();
+    assert_eq!(cues.cue_points[0].position_ms, 100.0);
+    assert_eq!(nml.entry(&rwfile).unwrap().artist.as_deref(), Some("Apple"));
+    assert!(nml.to_xml().contains(r#"ARTIST="Apple""#));
+
+    let report = nml.sync(SyncMode::TwoWay, &[Vendor::Serato]).unwrap();
+    assert!(report.to_tags.is_empty() && report.to_collection.is_empty());
+    assert_eq!(report.conflicts.len(), 1);
+
+    let report = nml.sync(SyncMode::ToTags, &[Vendor::Serato]).unwrap();
+    assert!(report.conflicts.is_empty());
+    assert_eq!(ID3rs::read(&rwfile).unwrap().title(), Some("Tonk"));
+  }
+
+  #[test]
+  fn test_set_entry_key_and_rating() {
+    let mut nml = Nml::parse(COLLECTION).unwrap();
+    let mut entry = nml.entries().remove(0);
+    entry.key = Some(MusicalKey::Minor(9));
+    entry.rating = Some(9);
+    assert!(nml.set_entry(&entry, &[Field::Key, Field::Rating]));
+    let xml = nml.to_xml();
+    assert!(xml.contains(r#"KEY="1m""#), "open key notation");
+    assert!(xml.contains(r#"RANKING="255""#));
+  }
+
+  #[test]
+  fn test_sync_unreadable_tag() {
+    let dir = tempfile::tempdir().unwrap();
+    make_rwcopy("samples/5eep.mp3", dir.path().join("4tink.mp3").to_str().unwrap()).unwrap();
+    let mut nml = Nml::parse(&collection(dir.path())).unwrap();
+    let report = nml.sync(SyncMode::TwoWay, &[]).unwrap();
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.to_tags.is_empty() && report.to_collection.is_empty());
+  }
+
+  #[test]
+  fn test_sync_missing_file() {
+    let mut nml = Nml::parse(&COLLECTION.replace("{dir}", "/:nowhere/:")).unwrap();
+    let report = nml.sync(SyncMode::ToCollection, &[]).unwrap();
+    assert_eq!(report.missing, [Path::new("/nowhere/4tink.mp3")]);
+  }
+}
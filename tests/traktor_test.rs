@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::musical_key::MusicalKey;
+  use id3rs::traktor::{Cue, CueKind, Traktor, TRAKTOR_OWNER};
+  use id3rs::{log_init, make_rwcopy, ID3rs};
+
+  #[test]
+  fn test_read_traktor() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let traktor = Traktor::read(&tag).unwrap().unwrap();
+    assert_eq!(&traktor.root.id, b"TRMD");
+    assert!(traktor.find("TIT2").is_some());
+    assert_eq!(traktor.key(), Some(MusicalKey::Minor(9)));
+    assert_eq!(traktor.bpm().unwrap().round(), 124.0);
+
+    let markers = traktor.grid_markers().unwrap();
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0].name, "AutoGrid");
+    assert_eq!(markers[0].kind, CueKind::Grid);
+    assert!(traktor.cues().unwrap().is_empty());
+    assert!(traktor.loops().unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_round_trip_unchanged() {
+    log_init();
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let data = tag.private(TRAKTOR_OWNER).unwrap();
+    assert_eq!(Traktor::parse(data).unwrap().to_bytes(), data);
+  }
+
+  #[test]
+  fn test_round_trip_opaque_chunk() {
+    // claims 2 children that do not parse, with an id byte outside ASCII
+    let mut data = b"\xE9DIV".to_vec();
+    data.extend(4u32.to_le_bytes());
+    data.extend(2u32.to_le_bytes());
+    data.extend(b"junk");
+    let traktor = Traktor::parse(&data).unwrap();
+    assert_eq!(&traktor.root.id, b"VID\xE9");
+    assert!(traktor.root.children.is_empty());
+    assert_eq!(traktor.to_bytes(), data);
+  }
+
+  #[test]
+  fn test_deeply_nested_chunks() {
+    let mut data = vec![];
+    for _ in 0..100_000 {
+      let mut parent = b"DIVD".to_vec();
+      parent.extend((data.len() as u32).to_le_bytes());
+      parent.extend(1u32.to_le_bytes());
+      parent.extend(data);
+      data = parent;
+    }
+    let traktor = Traktor::parse(&data).unwrap();
+    let mut depth = 0;
+    let mut chunk = &traktor.root;
+    while let Some(child) = chunk.children.first() {
+      chunk = child;
+      depth += 1;
+    }
+    assert_eq!(depth, 16);
+    assert!(!chunk.data.is_empty(), "kept as data");
+    assert_eq!(traktor.to_bytes(), data);
+  }
+
+  #[test]
+  fn test_no_traktor() {
+    log_init();
+    let tag = ID3rs::read("samples/4tink.mp3").unwrap();
+    assert_eq!(Traktor::read(&tag).unwrap(), None);
+  }
+
+  #[test]
+  fn test_write_traktor() {
+    log_init();
+    let rwfile = format!("samples/psycho-traktor{}.mp3", rand::random::<u32>());
+    make_rwcopy("samples/psycho.mp3", &rwfile).unwrap();
+
+    let mut tag = ID3rs::read(&rwfile).unwrap();
+    let mut traktor = Traktor::read(&tag).unwrap().unwrap();
+    let mut cues = traktor.grid_markers().unwrap();
+    cues.push(Cue::new("Drop", CueKind::Cue, 61234.5, 0.0, 0));
+    cues.push(Cue::new("Break", CueKind::Loop, 92000.0, 7500.0, 1));
+    traktor.set_cues(&cues).unwrap();
+    traktor.set_key(MusicalKey::Major(7)).unwrap();
+    traktor.write(&mut tag);
+    tag.write_to(&rwfile).unwrap();
+
+    let tag = ID3rs::read(&rwfile).unwrap();
+    let traktor = Traktor::read(&tag).unwrap().unwrap();
+    assert_eq!(traktor.all_cues().unwrap(), cues);
+    assert_eq!(traktor.cues().unwrap()[0].name, "Drop");
+    assert_eq!(traktor.loops().unwrap()[0].length_ms, 7500.0);
+    assert_eq!(traktor.key(), Some(MusicalKey::Major(7)));
+    assert_eq!(traktor.grid_markers().unwrap()[0].name, "AutoGrid");
+    fs::remove_file(rwfile).unwrap();
+  }
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use id3rs::mp3_frame::{Layer, Version};
+  use id3rs::validator::{validate, Issue, ValidationReport};
+  use id3rs::vbr_header::VbrKind;
+  use id3rs::{ID3rs, ID3HEADER_SIZE};
+
+  const FRAME_SIZE: usize = 384;
+
+  fn audio() -> Vec<u8> {
+    fs::read("samples/4tink-raw.mp3").unwrap()
+  }
+
+  #[test]
+  fn test_valid() {
+    let report = validate("samples/4tink.mp3").unwrap();
+    assert!(report.is_valid(), "{:?}", report);
+    assert_eq!(report.version, Some(Version::Version1));
+    assert_eq!(report.layer, Some(Layer::Layer3));
+    assert_eq!(report.sample_rate, Some(48000));
+    assert_eq!(report.frames, 26);
+    assert_eq!(report.vbr_header, Some(VbrKind::Info));
+    assert_eq!(report, validate("samples/4tink-raw.mp3").unwrap());
+  }
+
+  #[test]
+  fn test_junk() {
+    let mut audio = audio();
+    audio.splice(3 * FRAME_SIZE..3 * FRAME_SIZE, *b"junk");
+    let report = ValidationReport::parse(&audio);
+    assert_eq!(report.issues, [Issue::Junk { offset: 3 * FRAME_SIZE as u64, len: 4 }]);
+    assert_eq!(report.frames, 26);
+  }
+
+  #[test]
+  fn test_truncated_sample() {
+    let report = validate("samples/psycho.mp3").unwrap();
+    assert_eq!(report.issues, [
+      Issue::Truncated { offset: 1044, len: 61, expected: 1044 },
+      Issue::FrameCount { header: 10009, found: 0 },
+    ]);
+  }
+
+  #[test]
+  fn test_truncated() {
+    let audio = audio();
+    let report = ValidationReport::parse(&audio[..audio.len() - 100]);
+    assert_eq!(report.issues, [
+      Issue::Truncated { offset: 25 * FRAME_SIZE as u64, len: FRAME_SIZE as u64 - 100, expected: FRAME_SIZE as u64 },
+      Issue::FrameCount { header: 25, found: 24 },
+    ]);
+  }
+
+  #[test]
+  fn test_id3v1() {
+    let mut audio = audio();
+    audio.extend(b"TAG");
+    audio.resize(audio.len() + 125, 0);
+    let report = ValidationReport::parse(&audio);
+    assert!(report.id3v1);
+    assert!(report.is_valid());
+  }
+
+  #[test]
+  fn test_inconsistent() {
+    let mut audio = audio();
+    // the first frame of psycho.mp3 is 44.1 kHz
+    let tag = ID3rs::read("samples/psycho.mp3").unwrap();
+    let start = (ID3HEADER_SIZE + tag.header_size) as usize;
+    audio.extend(&fs::read("samples/psycho.mp3").unwrap()[start..start + 1044]);
+    let report = ValidationReport::parse(&audio);
+    assert_eq!(report.issues, [
+      Issue::Inconsistent { offset: 26 * FRAME_SIZE as u64 },
+      Issue::FrameCount { header: 25, found: 26 },
+    ]);
+  }
+
+  #[test]
+  fn test_bad_crc() {
+    let mut audio = audio();
+    // protected, so the first bytes of the side information are taken for the CRC
+    audio[FRAME_SIZE + 1] &= 0xfe;
+    let report = ValidationReport::parse(&audio);
+    assert_eq!(report.issues, [Issue::BadCrc { offset: FRAME_SIZE as u64, len: FRAME_SIZE as u64 }]);
+    assert_eq!((report.protected_frames, report.corrupt_frames), (1, 1));
+
+    audio[2 * FRAME_SIZE + 1] &= 0xfe;
+    let report = ValidationReport::parse(&audio);
+    assert_eq!(report.issues, [Issue::BadCrc { offset: FRAME_SIZE as u64, len: 2 * FRAME_SIZE as u64 }]);
+    assert_eq!(report.corrupt_frames, 2);
+  }
+}
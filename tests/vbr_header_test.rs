@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+  use id3rs::mp3_frame::frame_header;
+  use id3rs::mp3_parser::Mp3FrameParser;
+  use id3rs::vbr_header::{LameTag, ReplayGain, Toc, VbrKind, VbrMethod};
+  use id3rs::ID3HEADER_SIZE;
+
+  fn frame(offset: usize, header: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0; 384];
+    frame[..4].copy_from_slice(b"\xFF\xFB\x94\x44");
+    frame[offset..offset + header.len()].copy_from_slice(header);
+    frame
+  }
+
+  fn first_frame(path: &str) -> Vec<u8> {
+    let bytes = std::fs::read(path).unwrap();
+    let size = bytes[6..10].iter().fold(0usize, |size, b| size << 7 | *b as usize);
+    bytes[ID3HEADER_SIZE as usize + size..].to_vec()
+  }
+
+  #[test]
+  fn test_lame_tag() {
+    let (_, frame) = frame_header(&first_frame("samples/4tink.mp3")).unwrap();
+    let lame = frame.vbr_header().unwrap().lame.unwrap();
+    assert_eq!(lame.encoder, "LAME3.100");
+    assert_eq!(lame.vbr_method, VbrMethod::Cbr);
+    assert_eq!(lame.lowpass_hz, 17000);
+    assert_eq!(lame.peak, None);
+    assert_eq!(lame.track_gain, Some(ReplayGain { originator: 3, gain_db: 1.2 }));
+    assert_eq!(lame.album_gain, None);
+    assert_eq!(lame.bitrate, 128);
+    assert_eq!(lame.gapless_samples(), (576, 1147));
+    assert_eq!(lame.preset_name(), Some("CBR 128".to_string()));
+    let abr = LameTag { vbr_method: VbrMethod::Abr, ..lame.clone() };
+    assert_eq!(abr.preset_name(), Some("ABR 128".to_string()));
+    assert_eq!(lame.music_length, 26 * 384);
+    assert!(lame.crc_ok);
+
+    let (_, frame) = frame_header(&first_frame("samples/psycho.mp3")).unwrap();
+    let lame = frame.vbr_header().unwrap().lame.unwrap();
+    assert_eq!(lame.encoder, "LAME3.99r");
+    assert_eq!(lame.lowpass_hz, 20500);
+    assert_eq!(lame.track_gain.unwrap().gain_db, -6.7);
+    assert_eq!((lame.delay, lame.padding), (576, 1464));
+    assert!(lame.crc_ok);
+  }
+
+  #[test]
+  fn test_lame_tag_crc() {
+    let mut audio = first_frame("samples/4tink.mp3");
+    let lame = audio.windows(4).position(|w| w == b"LAME").unwrap();
+    audio[lame + 10] ^= 0xff;
+    let (_, frame) = frame_header(&audio).unwrap();
+    assert!(!frame.vbr_header().unwrap().lame.unwrap().crc_ok);
+  }
+
+  #[test]
+  fn test_info_header() {
+    let mut frames = Mp3FrameParser::new("samples/4tink.mp3").unwrap();
+    let vbr = frames.next().unwrap().unwrap().vbr_header().unwrap();
+    assert_eq!(vbr.kind, VbrKind::Info);
+    assert_eq!(vbr.frames, Some(25));
+    assert_eq!(vbr.bytes, Some(26 * 384));
+    assert!(matches!(vbr.toc, Some(Toc::Xing(ref toc)) if toc.len() == 100));
+    assert!(vbr.quality.is_some());
+    assert_eq!(frames.next().unwrap().unwrap().vbr_header(), None);
+  }
+
+  #[test]
+  fn test_xing_seek() {
+    let mut header = b"Xing\x00\x00\x00\x07\x00\x00\x00\x64\x00\x00\x27\x10".to_vec();
+    header.extend((0..100).map(|percent| (percent * 256 / 100) as u8));
+    let (_, frame) = frame_header(&frame(36, &header)).unwrap();
+    let vbr = frame.vbr_header().unwrap();
+    assert_eq!((vbr.kind, vbr.frames, vbr.bytes, vbr.quality), (VbrKind::Xing, Some(100), Some(10000), None));
+    assert_eq!(vbr.seek(0.0), Some(0));
+    assert_eq!(vbr.seek(0.5), Some(5000));
+    assert_eq!(vbr.seek(1.0), Some(9999));
+  }
+
+  #[test]
+  fn test_vbri_header() {
+    let mut header = b"VBRI\x00\x01\x04\x62\x00\x4b\x00\x00\x27\x10\x00\x00\x00\x64\x00\x04\x00\x02\x00\x02\x00\x19".to_vec();
+    header.extend([0x04, 0xe2].repeat(4));
+    let (_, frame) = frame_header(&frame(36, &header)).unwrap();
+    let vbr = frame.vbr_header().unwrap();
+    assert_eq!((vbr.kind, vbr.frames, vbr.bytes, vbr.quality), (VbrKind::Vbri, Some(100), Some(10000), Some(75)));
+    assert_eq!(vbr.toc, Some(Toc::Vbri { frames_per_entry: 25, sizes: vec![2500; 4] }));
+    assert_eq!(vbr.seek(0.5), Some(5000));
+    assert_eq!(vbr.seek(0.6), Some(6000));
+  }
+
+  #[test]
+  fn test_vbri_size_overflow() {
+    let header = b"VBRI\x00\x01\x04\x62\x00\x4b\x00\x00\x27\x10\x00\x00\x00\x64\x00\x01\xff\xff\x00\x04\x00\x19\xff\xff\xff\xff";
+    let (_, frame) = frame_header(&frame(36, header)).unwrap();
+    assert_eq!(frame.vbr_header(), None, "scaled size overflows");
+  }
+}